@@ -0,0 +1,62 @@
+//! ALSA音频捕获模块
+//!
+//! 非Windows平台的默认捕获方式：启动 `arecord` 子进程录制默认ALSA设备，
+//! 以32位浮点立体声原始PCM的形式从管道读取数据。
+//!
+//! 注意：默认ALSA设备是录音设备（通常是麦克风），录到的并不是正在播放的声音。
+//! 要可视化系统播放的音频，需要把默认录音设备指向回环设备
+//! （如 `snd-aloop` 内核模块提供的 Loopback）或声卡的监听（monitor）设备
+
+use crate::audio::pipe::PipeSource;
+use crate::audio::{AudioSource, SampleType, StreamFormat};
+use anyhow::{Context, Result};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+const CHANNELS: u16 = 2; // 录制声道数
+const SAMPLE_RATE: u32 = 48000; // 录制采样率
+
+/// ALSA录音音频源
+pub struct AlsaSource {
+    child: Child,                  // arecord子进程
+    pipe: PipeSource<ChildStdout>, // 子进程输出管道
+}
+
+/// 启动 `arecord` 并返回ALSA音频源
+pub fn capture() -> Result<AlsaSource> {
+    let mut child = Command::new("arecord")
+        .args(["-q", "-t", "raw", "-f", "FLOAT_LE"])
+        .args(["-c", &CHANNELS.to_string(), "-r", &SAMPLE_RATE.to_string()])
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("无法启动 arecord，请确认已安装 alsa-utils")?;
+    let stdout = child.stdout.take().context("无法获取 arecord 输出管道")?;
+    println!(
+        "STAGE 1: arecord started, format is: {} ch, {} Hz",
+        CHANNELS, SAMPLE_RATE
+    );
+    // 默认录音设备通常是麦克风，提示用户改用回环或监听设备
+    eprintln!("注意：录制的是默认 ALSA 录音设备，显示播放的声音需使用回环（snd-aloop）或监听设备");
+    let format = StreamFormat::new(CHANNELS, SAMPLE_RATE, SampleType::F32);
+    Ok(AlsaSource {
+        child,
+        pipe: PipeSource::new(stdout, format),
+    })
+}
+
+impl AudioSource for AlsaSource {
+    fn format(&self) -> StreamFormat {
+        self.pipe.format()
+    }
+
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<usize> {
+        self.pipe.read(out)
+    }
+}
+
+impl Drop for AlsaSource {
+    fn drop(&mut self) {
+        // 结束录音子进程
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -3,22 +3,36 @@
 //! 该模块实现了基于Windows Core Audio API的系统音频捕获功能
 //! 支持捕获系统混音输出（Loopback模式），用于音频可视化
 
+use crate::audio::convert::{self, WAVE_FORMAT_EXTENSIBLE};
+use crate::audio::{AudioSource, StreamFormat};
 use anyhow::{Result, anyhow};
 use windows::{
     Win32::{
         Media::Audio::{
-            AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK, IAudioCaptureClient,
-            IAudioClient, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator, WAVEFORMATEX,
-            eConsole, eRender,
+            AUDCLNT_BUFFERFLAGS_SILENT, AUDCLNT_SHAREMODE_SHARED, AUDCLNT_STREAMFLAGS_LOOPBACK,
+            IAudioCaptureClient, IAudioClient, IMMDevice, IMMDeviceEnumerator, MMDeviceEnumerator,
+            WAVEFORMATEX, WAVEFORMATEXTENSIBLE, eConsole, eRender,
+        },
+        System::Com::{
+            CLSCTX_ALL, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx, CoTaskMemFree,
         },
-        System::Com::{CLSCTX_ALL, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx},
     },
     core::HRESULT,
 };
-/// 初始化并返回音频捕获客户端
+
+/// WASAPI回环音频源
+///
+/// 持有音频客户端与捕获客户端，按数据包读取系统混音输出
+pub struct WasapiSource {
+    audio_client: IAudioClient,          // 音频客户端
+    capture_client: IAudioCaptureClient, // 捕获客户端
+    format: StreamFormat,                // 协商得到的混音格式
+}
+
+/// 初始化并返回WASAPI回环音频源
 ///
 /// 完成完整的音频捕获初始化流程
-pub fn capture() -> Result<IAudioCaptureClient> {
+pub fn capture() -> Result<WasapiSource> {
     unsafe {
         // 初始化COM库，使用多线程模式
         // 这是使用Windows COM API的必要步骤
@@ -31,23 +45,133 @@ pub fn capture() -> Result<IAudioCaptureClient> {
         let device: IMMDevice = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
         let audio_client: IAudioClient = device.Activate::<IAudioClient>(CLSCTX_ALL, None)?;
         let audio_info: *mut WAVEFORMATEX = audio_client.GetMixFormat()?;
-        let channels: u16 = (*audio_info).nChannels;
-        let sample_rate: u32 = (*audio_info).nSamplesPerSec;
-        println!(
-            "STAGE 1: Get device successfully, format is: {} ch, {} Hz",
-            channels, sample_rate
-        );
-        audio_client.Initialize(
+        let format = mix_format(audio_info);
+        let initialized = audio_client.Initialize(
             AUDCLNT_SHAREMODE_SHARED,
             AUDCLNT_STREAMFLAGS_LOOPBACK,
             0,
             0,
             audio_info,
             None,
-        )?;
+        );
+        // 混音格式由COM分配，使用完毕后释放
+        CoTaskMemFree(Some(audio_info as *const _));
+        let format = format?;
+        println!(
+            "STAGE 1: Get device successfully, format is: {} ch, {} Hz, {:?}, mask {:#x}",
+            format.channels, format.sample_rate, format.sample_type, format.channel_mask
+        );
+        initialized?;
         let capture_client: IAudioCaptureClient = audio_client.GetService()?;
         audio_client.Start()?;
         println!("STAGE 2: Capture Started.");
-        Ok(capture_client)
+        Ok(WasapiSource {
+            audio_client,
+            capture_client,
+            format,
+        })
+    }
+}
+
+/// 解析混音格式
+///
+/// 支持WAVEFORMATEX与WAVEFORMATEXTENSIBLE，后者的实际采样类型
+/// 由子格式GUID给出，并携带声道掩码
+unsafe fn mix_format(audio_info: *const WAVEFORMATEX) -> Result<StreamFormat> {
+    // 结构体按1字节对齐，使用非对齐读取
+    let wave = unsafe { std::ptr::read_unaligned(audio_info) };
+    let channels = wave.nChannels;
+    let bits_per_sample = wave.wBitsPerSample;
+    let mut format_tag = wave.wFormatTag;
+    let mut channel_mask = convert::default_channel_mask(channels);
+    if format_tag == WAVE_FORMAT_EXTENSIBLE && wave.cbSize >= 22 {
+        let extensible =
+            unsafe { std::ptr::read_unaligned(audio_info as *const WAVEFORMATEXTENSIBLE) };
+        // 子格式GUID的data1字段即实际的格式标记
+        let sub_format = extensible.SubFormat;
+        format_tag = sub_format.data1 as u16;
+        let mask = extensible.dwChannelMask;
+        if mask.count_ones() == channels as u32 {
+            channel_mask = mask;
+        }
+    }
+    let sample_type =
+        convert::sample_type_from_tag(format_tag, bits_per_sample).ok_or_else(|| {
+            anyhow!(
+                "不支持的混音格式: 格式标记 {:#06x}, {} 位",
+                format_tag,
+                bits_per_sample
+            )
+        })?;
+    if channels == 0
+        || wave.nBlockAlign as usize != sample_type.bytes_per_sample() * channels as usize
+    {
+        return Err(anyhow!(
+            "无效的混音格式: {} ch, 块对齐 {}",
+            channels,
+            wave.nBlockAlign
+        ));
+    }
+    Ok(StreamFormat {
+        channels,
+        sample_rate: wave.nSamplesPerSec,
+        sample_type,
+        channel_mask,
+    })
+}
+
+impl AudioSource for WasapiSource {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    /// 读取一个WASAPI数据包
+    ///
+    /// 静音数据包按帧数填充0，保证时间轴连续
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<usize> {
+        // 检查是否有新的音频数据包
+        let packet_length = unsafe { self.capture_client.GetNextPacketSize() }
+            .map_err(|e| anyhow!("获取数据包大小失败: {:?}", e))?;
+        if packet_length == 0 {
+            return Ok(0);
+        }
+        // 准备接收音频数据的变量
+        let mut data_ptr: *mut u8 = std::ptr::null_mut(); // 数据指针
+        let mut num_frames: u32 = 0; // 帧数
+        let mut flags: u32 = 0; // 状态标志
+        // 获取音频缓冲区数据
+        unsafe {
+            self.capture_client.GetBuffer(
+                &mut data_ptr,   // 输出数据指针
+                &mut num_frames, // 输出帧数
+                &mut flags,      // 输出状态标志
+                None,            // 不需要时间戳
+                None,            // 不需要设备位置
+            )
+        }
+        .map_err(|e| anyhow!("获取音频缓冲区失败: {:?}", e))?;
+        if (flags & (AUDCLNT_BUFFERFLAGS_SILENT.0 as u32)) == 0 {
+            // 按协商格式的块对齐计算字节数，再解码为浮点采样
+            let raw_bytes: &[u8] = unsafe {
+                std::slice::from_raw_parts(
+                    data_ptr,
+                    num_frames as usize * self.format.bytes_per_frame(),
+                )
+            };
+            convert::decode_interleaved(raw_bytes, self.format.sample_type, out);
+        } else {
+            let sample_count = num_frames as usize * self.format.channels as usize;
+            out.resize(out.len() + sample_count, 0.0);
+        }
+        // 释放音频缓冲区
+        unsafe { self.capture_client.ReleaseBuffer(num_frames) }?;
+        Ok(num_frames as usize)
+    }
+}
+
+impl Drop for WasapiSource {
+    fn drop(&mut self) {
+        // 停止捕获流
+        let _ = unsafe { self.audio_client.Stop() };
     }
 }
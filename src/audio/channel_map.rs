@@ -0,0 +1,316 @@
+//! 声道映射模块
+//!
+//! 位于音频源与频谱分析之间，把任意声道布局的交错帧
+//! 按启动时选择的方式映射为单声道或立体声：
+//! 带系数的标准下混、仅前置左右、仅低频（用于低音表）或自选声道
+
+use crate::audio::StreamFormat;
+use crate::audio::convert::{
+    SPEAKER_BACK_CENTER, SPEAKER_BACK_LEFT, SPEAKER_BACK_RIGHT, SPEAKER_FRONT_CENTER,
+    SPEAKER_FRONT_LEFT, SPEAKER_FRONT_RIGHT, SPEAKER_LOW_FREQUENCY, SPEAKER_SIDE_LEFT,
+    SPEAKER_SIDE_RIGHT, channel_index,
+};
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::str::FromStr;
+
+const SPEAKER_FRONT_LEFT_OF_CENTER: u32 = 0x40;
+const SPEAKER_FRONT_RIGHT_OF_CENTER: u32 = 0x80;
+
+/// 扬声器名称与掩码位的对应关系
+const SPEAKER_NAMES: [(&str, u32); 11] = [
+    ("FL", SPEAKER_FRONT_LEFT),
+    ("FR", SPEAKER_FRONT_RIGHT),
+    ("FC", SPEAKER_FRONT_CENTER),
+    ("LFE", SPEAKER_LOW_FREQUENCY),
+    ("BL", SPEAKER_BACK_LEFT),
+    ("BR", SPEAKER_BACK_RIGHT),
+    ("FLC", SPEAKER_FRONT_LEFT_OF_CENTER),
+    ("FRC", SPEAKER_FRONT_RIGHT_OF_CENTER),
+    ("BC", SPEAKER_BACK_CENTER),
+    ("SL", SPEAKER_SIDE_LEFT),
+    ("SR", SPEAKER_SIDE_RIGHT),
+];
+
+/// 声道映射方式
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChannelMode {
+    Stereo,           // 按ITU-R BS.775系数下混为立体声（忽略LFE）
+    Mono,             // 下混为单声道
+    Front,            // 仅前置左右声道
+    Lfe,              // 仅低频声道，单声道输出
+    Select(Vec<u32>), // 自选扬声器：1个为单声道，2个为立体声，更多则平均为单声道
+}
+
+impl FromStr for ChannelMode {
+    type Err = String;
+
+    /// 解析 `stereo`、`mono`、`front`、`lfe` 或逗号分隔的扬声器名（如 `FL,FR`）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "stereo" => return Ok(ChannelMode::Stereo),
+            "mono" => return Ok(ChannelMode::Mono),
+            "front" => return Ok(ChannelMode::Front),
+            "lfe" => return Ok(ChannelMode::Lfe),
+            _ => {}
+        }
+        let speakers = s
+            .split(',')
+            .map(|name| {
+                let name = name.trim().to_ascii_uppercase();
+                SPEAKER_NAMES
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|&(_, speaker)| speaker)
+                    .ok_or_else(|| {
+                        let names: Vec<&str> = SPEAKER_NAMES.iter().map(|(n, _)| *n).collect();
+                        format!(
+                            "未知的扬声器 `{}`，可选: stereo, mono, front, lfe 或 {}",
+                            name,
+                            names.join("/")
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ChannelMode::Select(speakers))
+    }
+}
+
+/// 声道映射矩阵
+///
+/// 输出第o个声道 = Σ matrix[o][i] × 输入第i个声道
+pub struct ChannelMap {
+    in_channels: usize,  // 输入声道数
+    out_channels: usize, // 输出声道数（1或2）
+    matrix: Vec<f32>,    // 按行存储的映射系数，out_channels × in_channels
+}
+
+impl ChannelMap {
+    /// 根据映射方式和输入流的声道布局构建映射矩阵
+    pub fn new(mode: &ChannelMode, format: &StreamFormat) -> Self {
+        let in_channels = format.channels as usize;
+        let mask = format.channel_mask;
+        // 查找扬声器所在的输入声道；掩码缺失时前两个声道按左右处理
+        let find = |speaker: u32| -> Option<usize> {
+            if mask == 0 {
+                return match speaker {
+                    SPEAKER_FRONT_LEFT => Some(0),
+                    SPEAKER_FRONT_RIGHT => Some(1.min(in_channels - 1)),
+                    _ => None,
+                };
+            }
+            channel_index(mask, speaker).filter(|&i| i < in_channels)
+        };
+        let (out_channels, mut matrix) = match mode {
+            ChannelMode::Stereo | ChannelMode::Mono => {
+                let mut matrix = vec![0.0f32; 2 * in_channels];
+                let has_front =
+                    find(SPEAKER_FRONT_LEFT).is_some() || find(SPEAKER_FRONT_RIGHT).is_some();
+                // 各扬声器对左右输出的下混系数
+                let center = if has_front { FRAC_1_SQRT_2 } else { 1.0 };
+                let coefficients = [
+                    (SPEAKER_FRONT_LEFT, 1.0, 0.0),
+                    (SPEAKER_FRONT_RIGHT, 0.0, 1.0),
+                    (SPEAKER_FRONT_CENTER, center, center),
+                    (SPEAKER_FRONT_LEFT_OF_CENTER, 1.0, 0.0),
+                    (SPEAKER_FRONT_RIGHT_OF_CENTER, 0.0, 1.0),
+                    (SPEAKER_BACK_LEFT, FRAC_1_SQRT_2, 0.0),
+                    (SPEAKER_BACK_RIGHT, 0.0, FRAC_1_SQRT_2),
+                    (SPEAKER_BACK_CENTER, 0.5, 0.5),
+                    (SPEAKER_SIDE_LEFT, FRAC_1_SQRT_2, 0.0),
+                    (SPEAKER_SIDE_RIGHT, 0.0, FRAC_1_SQRT_2),
+                ];
+                for (speaker, left, right) in coefficients {
+                    if let Some(i) = find(speaker) {
+                        matrix[i] += left;
+                        matrix[in_channels + i] += right;
+                    }
+                }
+                if *mode == ChannelMode::Mono {
+                    let mono = (0..in_channels)
+                        .map(|i| 0.5 * (matrix[i] + matrix[in_channels + i]))
+                        .collect();
+                    (1, mono)
+                } else {
+                    (2, matrix)
+                }
+            }
+            ChannelMode::Front => Self::selection(
+                &[SPEAKER_FRONT_LEFT, SPEAKER_FRONT_RIGHT],
+                in_channels,
+                find,
+            ),
+            ChannelMode::Lfe => Self::selection(&[SPEAKER_LOW_FREQUENCY], in_channels, find),
+            ChannelMode::Select(speakers) => Self::selection(speakers, in_channels, find),
+        };
+        // 行系数之和超过1时归一化，避免下混后溢出
+        for row in matrix.chunks_mut(in_channels) {
+            let sum: f32 = row.iter().sum();
+            if sum > 1.0 {
+                row.iter_mut().for_each(|c| *c /= sum);
+            }
+        }
+        if matrix.iter().all(|&c| c == 0.0) {
+            eprintln!("声道映射 {:?} 在当前声道布局中没有可用的声道", mode);
+        }
+        Self {
+            in_channels,
+            out_channels,
+            matrix,
+        }
+    }
+
+    /// 自选扬声器的映射：1个为单声道，2个为立体声，更多则平均为单声道
+    fn selection(
+        speakers: &[u32],
+        in_channels: usize,
+        find: impl Fn(u32) -> Option<usize>,
+    ) -> (usize, Vec<f32>) {
+        if speakers.len() == 2 {
+            let mut matrix = vec![0.0f32; 2 * in_channels];
+            for (o, &speaker) in speakers.iter().enumerate() {
+                if let Some(i) = find(speaker) {
+                    matrix[o * in_channels + i] = 1.0;
+                }
+            }
+            return (2, matrix);
+        }
+        let mut matrix = vec![0.0f32; in_channels];
+        for &speaker in speakers {
+            if let Some(i) = find(speaker) {
+                matrix[i] += 1.0 / speakers.len() as f32;
+            }
+        }
+        (1, matrix)
+    }
+
+    /// 输出声道数
+    pub fn out_channels(&self) -> usize {
+        self.out_channels
+    }
+
+    /// 对交错帧执行映射，结果以交错排列追加到 `out`
+    pub fn apply(&self, frames: &[f32], out: &mut Vec<f32>) {
+        for frame in frames.chunks_exact(self.in_channels) {
+            for row in self.matrix.chunks_exact(self.in_channels) {
+                out.push(row.iter().zip(frame).map(|(c, s)| c * s).sum());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::SampleType;
+
+    /// 按默认布局构建映射，返回只有第 `channel` 个输入声道为1时的输出
+    fn impulse(mode: &str, channels: u16, channel: usize) -> Vec<f32> {
+        let map = ChannelMap::new(
+            &mode.parse().unwrap(),
+            &StreamFormat::new(channels, 48000, SampleType::F32),
+        );
+        let mut frame = vec![0.0; channels as usize];
+        frame[channel] = 1.0;
+        let mut out = Vec::new();
+        map.apply(&frame, &mut out);
+        assert_eq!(out.len(), map.out_channels());
+        out
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-3, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("stereo".parse(), Ok(ChannelMode::Stereo));
+        assert_eq!("MONO".parse(), Ok(ChannelMode::Mono));
+        assert_eq!("front".parse(), Ok(ChannelMode::Front));
+        assert_eq!("lfe".parse(), Ok(ChannelMode::Lfe));
+        assert_eq!(
+            "FL,FR".parse(),
+            Ok(ChannelMode::Select(vec![
+                SPEAKER_FRONT_LEFT,
+                SPEAKER_FRONT_RIGHT
+            ]))
+        );
+        assert_eq!(
+            " fc , lfe ".parse(),
+            Ok(ChannelMode::Select(vec![
+                SPEAKER_FRONT_CENTER,
+                SPEAKER_LOW_FREQUENCY
+            ]))
+        );
+        assert!("FL,XX".parse::<ChannelMode>().is_err());
+    }
+
+    #[test]
+    fn stereo_passes_through_2_0() {
+        assert_close(&impulse("stereo", 2, 0), &[1.0, 0.0]);
+        assert_close(&impulse("stereo", 2, 1), &[0.0, 1.0]);
+        assert_close(&impulse("mono", 2, 0), &[0.5]);
+    }
+
+    #[test]
+    fn stereo_downmix_5_1() {
+        // FL FR FC LFE BL BR：左行系数和 1 + 2×0.707 归一化为1
+        let sum = 1.0 + 2.0 * FRAC_1_SQRT_2;
+        assert_close(&impulse("stereo", 6, 0), &[1.0 / sum, 0.0]);
+        assert_close(&impulse("stereo", 6, 1), &[0.0, 0.414]);
+        assert_close(&impulse("stereo", 6, 2), &[0.293, 0.293]);
+        assert_close(&impulse("stereo", 6, 3), &[0.0, 0.0]);
+        assert_close(&impulse("stereo", 6, 4), &[0.293, 0.0]);
+        assert_close(&impulse("stereo", 6, 5), &[0.0, 0.293]);
+        assert_close(&impulse("mono", 6, 2), &[0.293]);
+        assert_close(&impulse("front", 6, 0), &[1.0, 0.0]);
+        assert_close(&impulse("front", 6, 2), &[0.0, 0.0]);
+        assert_close(&impulse("lfe", 6, 3), &[1.0]);
+        assert_close(&impulse("lfe", 6, 0), &[0.0]);
+    }
+
+    #[test]
+    fn stereo_downmix_7_1() {
+        // FL FR FC LFE BL BR SL SR：左行系数和 1 + 3×0.707
+        let sum = 1.0 + 3.0 * FRAC_1_SQRT_2;
+        let surround = FRAC_1_SQRT_2 / sum;
+        assert_close(&impulse("stereo", 8, 0), &[1.0 / sum, 0.0]);
+        assert_close(&impulse("stereo", 8, 2), &[surround, surround]);
+        assert_close(&impulse("stereo", 8, 3), &[0.0, 0.0]);
+        assert_close(&impulse("stereo", 8, 6), &[surround, 0.0]);
+        assert_close(&impulse("stereo", 8, 7), &[0.0, surround]);
+        assert_close(&impulse("lfe", 8, 3), &[1.0]);
+    }
+
+    #[test]
+    fn selects_three_speakers_as_mono_average() {
+        for channel in [0, 2, 3] {
+            assert_close(&impulse("FL,FC,LFE", 6, channel), &[1.0 / 3.0]);
+        }
+        assert_close(&impulse("FL,FC,LFE", 6, 1), &[0.0]);
+        assert_close(&impulse("FC", 6, 2), &[1.0]);
+        assert_close(&impulse("BR,BL", 6, 4), &[0.0, 1.0]);
+    }
+
+    #[test]
+    fn unknown_layout_treats_first_two_channels_as_front() {
+        // 超过8个声道没有默认掩码，前两个声道按左右处理，其余忽略
+        assert_eq!(
+            StreamFormat::new(10, 48000, SampleType::F32).channel_mask,
+            0
+        );
+        assert_close(&impulse("stereo", 10, 0), &[1.0, 0.0]);
+        assert_close(&impulse("stereo", 10, 1), &[0.0, 1.0]);
+        assert_close(&impulse("stereo", 10, 5), &[0.0, 0.0]);
+        assert_close(&impulse("front", 10, 1), &[0.0, 1.0]);
+        assert_close(&impulse("lfe", 10, 3), &[0.0]);
+    }
+}
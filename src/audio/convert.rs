@@ -0,0 +1,136 @@
+//! 采样格式转换模块
+//!
+//! 将各后端协商出的原始采样（i16、紧凑i24、i32、f32）解码为f32，
+//! 并提供单声道到7.1的声道掩码定义
+
+use crate::audio::SampleType;
+
+pub const WAVE_FORMAT_PCM: u16 = 0x0001; // 整数PCM
+pub const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003; // 浮点PCM
+pub const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE; // 扩展格式，实际类型由子格式GUID给出
+
+// 扬声器位置掩码，与WAVEFORMATEXTENSIBLE的dwChannelMask定义一致
+pub const SPEAKER_FRONT_LEFT: u32 = 0x1;
+pub const SPEAKER_FRONT_RIGHT: u32 = 0x2;
+pub const SPEAKER_FRONT_CENTER: u32 = 0x4;
+pub const SPEAKER_LOW_FREQUENCY: u32 = 0x8;
+pub const SPEAKER_BACK_LEFT: u32 = 0x10;
+pub const SPEAKER_BACK_RIGHT: u32 = 0x20;
+pub const SPEAKER_BACK_CENTER: u32 = 0x100;
+pub const SPEAKER_SIDE_LEFT: u32 = 0x200;
+pub const SPEAKER_SIDE_RIGHT: u32 = 0x400;
+
+/// 由格式标记和采样位宽确定采样类型，不支持的组合返回None
+pub fn sample_type_from_tag(format_tag: u16, bits_per_sample: u16) -> Option<SampleType> {
+    match (format_tag, bits_per_sample) {
+        (WAVE_FORMAT_PCM, 16) => Some(SampleType::I16),
+        (WAVE_FORMAT_PCM, 24) => Some(SampleType::I24),
+        (WAVE_FORMAT_PCM, 32) => Some(SampleType::I32),
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => Some(SampleType::F32),
+        _ => None,
+    }
+}
+
+/// 未给出声道掩码时按声道数推断的默认布局（单声道到7.1）
+pub fn default_channel_mask(channels: u16) -> u32 {
+    match channels {
+        1 => SPEAKER_FRONT_CENTER,
+        2 => SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT,
+        3 => SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT | SPEAKER_FRONT_CENTER,
+        4 => SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT | SPEAKER_BACK_LEFT | SPEAKER_BACK_RIGHT,
+        5 => default_channel_mask(4) | SPEAKER_FRONT_CENTER,
+        6 => default_channel_mask(5) | SPEAKER_LOW_FREQUENCY,
+        7 => default_channel_mask(6) | SPEAKER_BACK_CENTER,
+        8 => default_channel_mask(6) | SPEAKER_SIDE_LEFT | SPEAKER_SIDE_RIGHT,
+        _ => 0,
+    }
+}
+
+/// 指定扬声器在交错帧中的声道序号
+///
+/// 声道按掩码中置位的顺序（从低位到高位）排列
+pub fn channel_index(channel_mask: u32, speaker: u32) -> Option<usize> {
+    if channel_mask & speaker == 0 {
+        return None;
+    }
+    Some((channel_mask & (speaker - 1)).count_ones() as usize)
+}
+
+/// 将交错排列的原始字节解码为f32采样，末尾不足一个采样的字节被忽略
+pub fn decode_interleaved(bytes: &[u8], sample_type: SampleType, out: &mut Vec<f32>) {
+    out.extend(
+        bytes
+            .chunks_exact(sample_type.bytes_per_sample())
+            .map(|sample| sample_type.decode_le(sample)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], sample_type: SampleType) -> Vec<f32> {
+        let mut out = Vec::new();
+        decode_interleaved(bytes, sample_type, &mut out);
+        out
+    }
+
+    #[test]
+    fn decodes_i16_full_scale() {
+        let bytes: Vec<u8> = [i16::MIN, 0, i16::MAX, 16384]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert_eq!(
+            decode(&bytes, SampleType::I16),
+            [-1.0, 0.0, 32767.0 / 32768.0, 0.5]
+        );
+    }
+
+    #[test]
+    fn decodes_packed_i24_with_sign_extension() {
+        let bytes = [
+            0x00, 0x00, 0x80, // -8388608
+            0xFF, 0xFF, 0xFF, // -1
+            0xFF, 0xFF, 0x7F, // 8388607
+            0x00, 0x00, 0x40, // 4194304
+        ];
+        assert_eq!(
+            decode(&bytes, SampleType::I24),
+            [-1.0, -1.0 / 8_388_608.0, 8_388_607.0 / 8_388_608.0, 0.5]
+        );
+    }
+
+    #[test]
+    fn decodes_i32_full_scale() {
+        let bytes: Vec<u8> = [i32::MIN, i32::MAX, 1 << 30]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let samples = decode(&bytes, SampleType::I32);
+        assert_eq!(samples[0], -1.0);
+        assert!((samples[1] - 1.0).abs() < 1e-6);
+        assert_eq!(samples[2], 0.5);
+    }
+
+    #[test]
+    fn decodes_f32_unchanged() {
+        let values = [-1.0f32, 0.25, 1.0];
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(decode(&bytes, SampleType::F32), values);
+    }
+
+    #[test]
+    fn drops_trailing_partial_sample() {
+        for sample_type in [
+            SampleType::I16,
+            SampleType::I24,
+            SampleType::I32,
+            SampleType::F32,
+        ] {
+            let size = sample_type.bytes_per_sample();
+            let bytes = vec![0u8; size * 2 + size - 1];
+            assert_eq!(decode(&bytes, sample_type).len(), 2, "{:?}", sample_type);
+        }
+    }
+}
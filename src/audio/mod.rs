@@ -1 +1,118 @@
+//! 音频输入模块
+//!
+//! 定义统一的音频源接口 [`AudioSource`]，屏蔽各采集后端之间的差异，
+//! 使频谱分析与可视化部分不再直接依赖具体平台的音频API
+
+#[cfg(not(windows))]
+pub mod alsa;
+#[cfg(windows)]
 pub mod capture;
+pub mod channel_map;
+pub mod convert;
+pub mod pipe;
+#[cfg(not(windows))]
+pub mod pulse;
+pub mod wav;
+
+use anyhow::Result;
+
+/// 音频源原始采样的数据类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleType {
+    F32, // 32位浮点
+    I16, // 16位有符号整数
+    I24, // 24位有符号整数（紧凑排列）
+    I32, // 32位有符号整数
+}
+
+impl SampleType {
+    /// 单个采样占用的字节数
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            SampleType::F32 | SampleType::I32 => 4,
+            SampleType::I16 => 2,
+            SampleType::I24 => 3,
+        }
+    }
+
+    /// 将一个小端序采样解码为[-1.0, 1.0]范围的f32
+    pub fn decode_le(self, bytes: &[u8]) -> f32 {
+        match self {
+            SampleType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            SampleType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            SampleType::I24 => {
+                // 放入i32高24位后算术右移，完成符号扩展
+                let v = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+                v as f32 / 8_388_608.0
+            }
+            SampleType::I32 => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32
+                    / 2_147_483_648.0
+            }
+        }
+    }
+}
+
+/// 音频流格式信息
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamFormat {
+    pub channels: u16,           // 声道数
+    pub sample_rate: u32,        // 采样率（Hz）
+    pub sample_type: SampleType, // 原始采样类型
+    pub channel_mask: u32,       // 声道掩码，与WAVEFORMATEXTENSIBLE的dwChannelMask定义一致
+}
+
+impl StreamFormat {
+    /// 按声道数推断默认声道布局创建流格式
+    pub fn new(channels: u16, sample_rate: u32, sample_type: SampleType) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            sample_type,
+            channel_mask: convert::default_channel_mask(channels),
+        }
+    }
+
+    /// 每帧占用的字节数
+    pub fn bytes_per_frame(&self) -> usize {
+        self.sample_type.bytes_per_sample() * self.channels as usize
+    }
+}
+
+/// 音频源接口
+///
+/// 各采集后端（WASAPI回环、文件、测试替身等）均实现该接口，
+/// 由音频线程统一拉取数据送入频谱分析
+pub trait AudioSource {
+    /// 返回音频流格式
+    fn format(&self) -> StreamFormat;
+
+    /// 读取一批音频帧
+    ///
+    /// 以交错排列（interleaved）的f32采样追加到 `out` 末尾，
+    /// 返回本次读取的帧数；暂无可用数据时返回0
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<usize>;
+}
+
+/// 打开当前平台默认的系统音频捕获
+///
+/// Windows下使用WASAPI回环捕获
+#[cfg(windows)]
+pub fn open_default() -> Result<Box<dyn AudioSource>> {
+    Ok(Box::new(capture::capture()?))
+}
+
+/// 打开当前平台默认的系统音频捕获
+///
+/// 优先录制PulseAudio/PipeWire默认输出设备的monitor源，
+/// 音频服务器不可用时退回ALSA录音
+#[cfg(not(windows))]
+pub fn open_default() -> Result<Box<dyn AudioSource>> {
+    match pulse::capture() {
+        Ok(source) => Ok(Box::new(source)),
+        Err(e) => {
+            eprintln!("monitor capture failed, falling back to ALSA: {:?}", e);
+            Ok(Box::new(alsa::capture()?))
+        }
+    }
+}
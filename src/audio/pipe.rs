@@ -0,0 +1,83 @@
+//! 字节流音频源
+//!
+//! 从任意 `Read` 字节流中读取交错排列的原始PCM数据，
+//! 适用于子进程管道、标准输入等没有帧边界的输入
+
+use crate::audio::{AudioSource, StreamFormat, convert};
+use anyhow::{Result, anyhow};
+use std::io::{ErrorKind, Read};
+
+const READ_CHUNK: usize = 4096; // 单次读取的最大字节数（单帧更长时按一帧计）
+
+/// 原始PCM字节流音频源
+pub struct PipeSource<R: Read> {
+    reader: R,            // 字节流
+    format: StreamFormat, // 流格式
+    buffer: Vec<u8>,      // 读取缓冲区，可能残留不足一帧的字节
+    pending: usize,       // 缓冲区中尚未解码的字节数
+}
+
+impl<R: Read> PipeSource<R> {
+    pub fn new(reader: R, format: StreamFormat) -> Self {
+        // 缓冲区至少容纳一整帧，否则残留字节填满后再也读不到完整帧
+        let len = READ_CHUNK.max(format.bytes_per_frame());
+        Self {
+            reader,
+            format,
+            buffer: vec![0u8; len],
+            pending: 0,
+        }
+    }
+}
+
+impl<R: Read> AudioSource for PipeSource<R> {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    /// 阻塞读取一块数据，解码其中的完整帧
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<usize> {
+        let n = match self.reader.read(&mut self.buffer[self.pending..]) {
+            Ok(0) => return Err(anyhow!("音频流已结束")),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let available = self.pending + n;
+        let frame_bytes = self.format.bytes_per_frame();
+        let frames = available / frame_bytes;
+        let used = frames * frame_bytes;
+        convert::decode_interleaved(&self.buffer[..used], self.format.sample_type, out);
+        // 将不足一帧的残留字节移到缓冲区开头
+        self.buffer.copy_within(used..available, 0);
+        self.pending = available - used;
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::SampleType;
+
+    #[test]
+    fn reads_frames_larger_than_read_chunk() {
+        // 1025声道f32le，一帧4100字节，超过单次读取的4096字节
+        let format = StreamFormat::new(1025, 48000, SampleType::F32);
+        let frame_bytes = format.bytes_per_frame();
+        assert!(frame_bytes > READ_CHUNK);
+        let bytes: Vec<u8> = (0..2 * 1025)
+            .flat_map(|i| (i as f32 / 4096.0).to_le_bytes())
+            .collect();
+        let mut source = PipeSource::new(bytes.as_slice(), format);
+        let mut out = Vec::new();
+        let mut frames = 0;
+        while frames < 2 {
+            frames += source.read(&mut out).unwrap();
+        }
+        assert_eq!(frames, 2);
+        assert_eq!(out.len(), 2 * 1025);
+        assert_eq!(out[1025], 1025.0 / 4096.0);
+        assert!(source.read(&mut out).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! PulseAudio / PipeWire 音频捕获模块
+//!
+//! Linux下与WASAPI回环对应的方式是录制默认输出设备（sink）的 `.monitor` 源。
+//! 通过 `pactl` 查询默认输出设备，再启动 `parec` 子进程录制其monitor。
+//! 后台线程定期查询默认设备，变化时自动切换到新设备的monitor；
+//! `parec` 意外退出（如音频服务器重启）时按退避间隔重新启动
+
+use crate::audio::pipe::PipeSource;
+use crate::audio::{AudioSource, SampleType, StreamFormat};
+use anyhow::{Context, Result, bail};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+const CHANNELS: u16 = 2; // 录制声道数
+const SAMPLE_RATE: u32 = 48000; // 录制采样率
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1); // 默认设备检查间隔
+const FOLLOW_ERROR_INTERVAL: Duration = Duration::from_secs(60); // 查询持续失败时重复报错的间隔
+const RESTART_DELAY: Duration = Duration::from_millis(250); // parec退出后首次重启前的等待时间
+const MAX_RESTART_DOUBLINGS: u32 = 4; // 连续重启时等待时间最多翻倍的次数
+
+/// monitor源录音音频源
+pub struct PulseSource {
+    sink: String,                  // 当前跟随的输出设备名
+    child: Child,                  // parec子进程
+    pipe: PipeSource<ChildStdout>, // 子进程输出管道
+    sinks: Receiver<String>,       // 后台线程查询到的默认输出设备名
+    watching: Arc<AtomicBool>,     // 后台线程是否继续查询，音频源释放时清除
+    restarts: u32,                 // 连续重启parec的次数，读到数据后清零
+}
+
+/// 连接音频服务器并返回默认输出设备的monitor音频源
+pub fn capture() -> Result<PulseSource> {
+    let sink = default_sink()?;
+    let (child, pipe) = record_monitor(&sink)?;
+    println!(
+        "STAGE 1: Recording {}.monitor, format is: {} ch, {} Hz",
+        sink, CHANNELS, SAMPLE_RATE
+    );
+    let watching = Arc::new(AtomicBool::new(true));
+    Ok(PulseSource {
+        sink,
+        child,
+        pipe,
+        sinks: watch_default_sink(watching.clone()),
+        watching,
+        restarts: 0,
+    })
+}
+
+/// 启动后台线程，每隔 `FOLLOW_INTERVAL` 查询一次默认输出设备
+///
+/// `pactl` 在后台线程中执行，不阻塞采集线程；音频源释放时清除 `watching`，线程随之退出。
+/// 查询持续失败时只在首次和此后每隔 `FOLLOW_ERROR_INTERVAL` 报错一次
+fn watch_default_sink(watching: Arc<AtomicBool>) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut last_error: Option<Instant> = None;
+        loop {
+            std::thread::sleep(FOLLOW_INTERVAL);
+            if !watching.load(Ordering::Relaxed) {
+                break;
+            }
+            match default_sink() {
+                Ok(sink) => {
+                    last_error = None;
+                    if sender.send(sink).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    if last_error.is_none_or(|at| at.elapsed() >= FOLLOW_ERROR_INTERVAL) {
+                        eprintln!("检查默认输出设备失败: {:?}", e);
+                        last_error = Some(Instant::now());
+                    }
+                }
+            }
+        }
+    });
+    receiver
+}
+
+/// 查询服务器的默认输出设备名
+fn default_sink() -> Result<String> {
+    let output = Command::new("pactl")
+        .arg("info")
+        .env("LC_ALL", "C") // 保证输出字段为英文
+        .output()
+        .context("无法启动 pactl，请确认已安装 PulseAudio 或 pipewire-pulse")?;
+    if !output.status.success() {
+        bail!("pactl info 执行失败: {}", output.status);
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("Default Sink: "))
+        .map(|sink| sink.trim().to_string())
+        .context("音频服务器没有默认输出设备")
+}
+
+/// 启动 `parec` 录制指定输出设备的monitor源
+fn record_monitor(sink: &str) -> Result<(Child, PipeSource<ChildStdout>)> {
+    let mut child = Command::new("parec")
+        .arg(format!("--device={}.monitor", sink))
+        .args(["--raw", "--format=float32le", "--latency-msec=10"])
+        .arg(format!("--channels={}", CHANNELS))
+        .arg(format!("--rate={}", SAMPLE_RATE))
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("无法启动 parec")?;
+    let stdout = child.stdout.take().context("无法获取 parec 输出管道")?;
+    let format = StreamFormat::new(CHANNELS, SAMPLE_RATE, SampleType::F32);
+    Ok((child, PipeSource::new(stdout, format)))
+}
+
+impl PulseSource {
+    /// 重新启动parec录制 `sink` 的monitor，替换原来的子进程
+    fn restart(&mut self, sink: String) -> Result<()> {
+        let (child, pipe) = record_monitor(&sink)?;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        self.sink = sink;
+        self.child = child;
+        self.pipe = pipe;
+        Ok(())
+    }
+}
+
+impl AudioSource for PulseSource {
+    fn format(&self) -> StreamFormat {
+        self.pipe.format()
+    }
+
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<usize> {
+        // 只取后台线程已有的查询结果，不阻塞
+        if let Some(sink) = self.sinks.try_iter().last()
+            && sink != self.sink
+        {
+            match self.restart(sink) {
+                Ok(()) => println!("默认输出设备已切换，正在录制 {}.monitor", self.sink),
+                Err(e) => eprintln!("切换默认输出设备失败: {:?}", e),
+            }
+        }
+        match self.pipe.read(out) {
+            Ok(frames) => {
+                if frames > 0 {
+                    self.restarts = 0;
+                }
+                Ok(frames)
+            }
+            Err(e) => {
+                // parec退出时重新启动而不是结束音频流，连续失败时等待时间逐次翻倍
+                let delay = RESTART_DELAY * 2u32.pow(self.restarts.min(MAX_RESTART_DOUBLINGS));
+                eprintln!("parec 已停止（{}），{} ms 后重新启动", e, delay.as_millis());
+                std::thread::sleep(delay);
+                self.restarts += 1;
+                // 启动失败时保留已结束的管道，下次读取会再次出错并以更长的间隔重试
+                if let Err(e) = self.restart(self.sink.clone()) {
+                    eprintln!("重新启动 parec 失败: {:?}", e);
+                }
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl Drop for PulseSource {
+    fn drop(&mut self) {
+        // 停止后台查询线程并结束录音子进程
+        self.watching.store(false, Ordering::Relaxed);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
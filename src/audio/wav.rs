@@ -0,0 +1,223 @@
+//! WAV文件音频源
+//!
+//! 解析RIFF/WAVE文件头，按实时速率读出PCM数据，
+//! 使可视化效果在没有音频设备的机器上也能稳定复现
+
+use crate::audio::convert::{self, WAVE_FORMAT_EXTENSIBLE};
+use crate::audio::{AudioSource, StreamFormat};
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Instant;
+
+const BLOCK_FRAMES: u64 = 480; // 单次输出的帧数，与WASAPI数据包大小相当
+const FMT_MAX_LEN: u64 = 40; // 需要解析的fmt块长度上限，即WAVEFORMATEXTENSIBLE的大小
+
+/// WAV文件音频源
+pub struct WavSource {
+    reader: BufReader<File>,  // 文件读取器，已定位到data块
+    format: StreamFormat,     // 流格式
+    remaining: u64,           // data块中剩余的帧数
+    frames_read: u64,         // 已输出的帧数
+    started: Option<Instant>, // 开始播放的时间，用于实时节奏控制
+    buffer: Vec<u8>,          // 原始字节缓冲区
+}
+
+/// 打开WAV文件并定位到音频数据
+pub fn open(path: &Path) -> Result<WavSource> {
+    let file = File::open(path).with_context(|| format!("无法打开 {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        bail!("{} 不是 RIFF/WAVE 文件", path.display());
+    }
+    let mut format = None;
+    // 依次遍历各个块，直到找到data块
+    loop {
+        let mut chunk = [0u8; 8];
+        reader
+            .read_exact(&mut chunk)
+            .context("WAV 文件中没有 data 块")?;
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        match &chunk[0..4] {
+            b"fmt " => {
+                // 不信任文件声明的块大小，只读取需要的部分，其余跳过
+                let len = size.min(FMT_MAX_LEN);
+                let mut fmt = [0u8; FMT_MAX_LEN as usize];
+                reader.read_exact(&mut fmt[..len as usize])?;
+                reader.seek(SeekFrom::Current((size - len) as i64))?;
+                format = Some(parse_fmt(&fmt[..len as usize])?);
+            }
+            b"data" => {
+                let format = format.ok_or_else(|| anyhow!("data 块出现在 fmt 块之前"))?;
+                let frame_bytes = format.bytes_per_frame() as u64;
+                println!(
+                    "STAGE 1: Open {} successfully, format is: {} ch, {} Hz, {:?}",
+                    path.display(),
+                    format.channels,
+                    format.sample_rate,
+                    format.sample_type
+                );
+                return Ok(WavSource {
+                    reader,
+                    format,
+                    remaining: size / frame_bytes,
+                    frames_read: 0,
+                    started: None,
+                    buffer: Vec::new(),
+                });
+            }
+            _ => {
+                // 跳过其他块，块大小为奇数时带一个填充字节
+                reader.seek(SeekFrom::Current((size + (size & 1)) as i64))?;
+                continue;
+            }
+        }
+        if size & 1 == 1 {
+            reader.seek(SeekFrom::Current(1))?;
+        }
+    }
+}
+
+/// 解析fmt块
+fn parse_fmt(fmt: &[u8]) -> Result<StreamFormat> {
+    if fmt.len() < 16 {
+        bail!("fmt 块长度不足");
+    }
+    let read_u16 = |at: usize| u16::from_le_bytes([fmt[at], fmt[at + 1]]);
+    let mut format_tag = read_u16(0);
+    let channels = read_u16(2);
+    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let bits_per_sample = read_u16(14);
+    let mut channel_mask = convert::default_channel_mask(channels);
+    if format_tag == WAVE_FORMAT_EXTENSIBLE {
+        if fmt.len() < 26 {
+            bail!("WAVE_FORMAT_EXTENSIBLE 的 fmt 块长度不足");
+        }
+        // 子格式GUID的前两个字节即实际的格式标记
+        format_tag = read_u16(24);
+        let mask = u32::from_le_bytes([fmt[20], fmt[21], fmt[22], fmt[23]]);
+        if mask.count_ones() == channels as u32 {
+            channel_mask = mask;
+        }
+    }
+    let sample_type =
+        convert::sample_type_from_tag(format_tag, bits_per_sample).ok_or_else(|| {
+            anyhow!(
+                "不支持的 WAV 格式: 格式标记 {:#06x}, {} 位",
+                format_tag,
+                bits_per_sample
+            )
+        })?;
+    if channels == 0 || sample_rate == 0 {
+        bail!("无效的 WAV 格式: {} ch, {} Hz", channels, sample_rate);
+    }
+    Ok(StreamFormat {
+        channels,
+        sample_rate,
+        sample_type,
+        channel_mask,
+    })
+}
+
+impl AudioSource for WavSource {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    /// 按墙钟时间输出已到期的帧，不足一个块时返回0
+    fn read(&mut self, out: &mut Vec<f32>) -> Result<usize> {
+        if self.remaining == 0 {
+            return Err(anyhow!("WAV 文件播放结束"));
+        }
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let due = (started.elapsed().as_secs_f64() * self.format.sample_rate as f64) as u64;
+        let pending = due.saturating_sub(self.frames_read);
+        if pending < BLOCK_FRAMES.min(self.remaining) {
+            return Ok(0);
+        }
+        let frames = pending.min(self.remaining);
+        self.buffer
+            .resize(frames as usize * self.format.bytes_per_frame(), 0);
+        self.reader.read_exact(&mut self.buffer)?;
+        convert::decode_interleaved(&self.buffer, self.format.sample_type, out);
+        self.frames_read += frames;
+        self.remaining -= frames;
+        Ok(frames as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::SampleType;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 写入只含fmt块和data块的WAV文件，返回其路径
+    fn write_wav(name: &str, fmt_size: u32, fmt: &[u8], data: &[u8]) -> std::path::PathBuf {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&fmt_size.to_le_bytes());
+        bytes.extend_from_slice(fmt);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        // 进程号加计数器，并行运行的测试不会共用同一个文件
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "{}-{}-{}.wav",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// 16位立体声44.1 kHz的PCM fmt块，`extra` 为附加的扩展字节
+    fn pcm_fmt(extra: &[u8]) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes()); // WAVE_FORMAT_PCM
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&44100u32.to_le_bytes());
+        fmt.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+        fmt.extend_from_slice(&4u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+        fmt.extend_from_slice(extra);
+        fmt
+    }
+
+    #[test]
+    fn skips_oversized_fmt_extension() {
+        // fmt块共64字节，超出40字节的部分被跳过
+        let fmt = pcm_fmt(&[0u8; 48]);
+        let path = write_wav("fmt-extension", fmt.len() as u32, &fmt, &[0u8; 16]);
+        let source = open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(source.format().channels, 2);
+        assert_eq!(source.format().sample_rate, 44100);
+        assert_eq!(source.format().sample_type, SampleType::I16);
+        assert_eq!(source.remaining, 4);
+    }
+
+    #[test]
+    fn rejects_malformed_fmt_sizes() {
+        // 声明的块大小远超文件长度：只读取前40字节，跳过其余部分后找不到data块，
+        // 而不是按声明大小分配内存
+        let path = write_wav("fmt-huge", u32::MAX, &pcm_fmt(&[]), &[0u8; 16]);
+        let err = format!("{:#}", open(&path).err().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.contains("没有 data 块"), "{}", err);
+
+        // 块不足16字节时由 `parse_fmt` 报错，其后的data块完整
+        let path = write_wav("fmt-short", 12, &pcm_fmt(&[])[..12], &[0u8; 16]);
+        let err = format!("{:#}", open(&path).err().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.contains("fmt 块长度不足"), "{}", err);
+    }
+}
@@ -0,0 +1,417 @@
+//! 命令行配置模块
+//!
+//! 解析启动参数，决定音频输入来源等运行配置
+
+use crate::audio::SampleType;
+use crate::audio::channel_map::ChannelMode;
+use crate::dsp::envelope::{BandSmoothing, Smoothing};
+use crate::dsp::fft::{AnalyzerMode, AnalyzerSettings};
+use crate::dsp::filterbank::BandFilter;
+use crate::dsp::normalize::{NormalizeMode, NormalizeSettings};
+use crate::dsp::onset::OnsetSettings;
+use crate::dsp::scale::BandScale;
+use crate::dsp::tempo::{self, TempoSettings};
+use crate::dsp::weighting::Weighting;
+use crate::dsp::window::{WindowCorrection, WindowKind};
+use crate::viz::peak::PeakSettings;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
+use std::path::PathBuf;
+
+/// 绝区零音乐可视化器
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Config {
+    /// 从WAV文件读取音频（按实时速率播放），不指定时捕获系统音频
+    #[arg(long, value_name = "FILE", conflicts_with = "stdin")]
+    pub input: Option<PathBuf>,
+
+    /// 从标准输入读取交错排列的原始PCM数据，例如 `ffmpeg -f f32le -` 的输出
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// 标准输入的采样格式
+    #[arg(long, value_enum, default_value_t = PcmFormat::F32le, requires = "stdin")]
+    pub format: PcmFormat,
+
+    /// 标准输入的采样率（Hz）
+    #[arg(
+        long,
+        default_value_t = 44100,
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "stdin"
+    )]
+    pub rate: u32,
+
+    /// 标准输入的声道数
+    #[arg(
+        long,
+        default_value_t = 2,
+        value_parser = clap::value_parser!(u16).range(1..),
+        requires = "stdin"
+    )]
+    pub channels: u16,
+
+    /// 声道映射方式：stereo（标准下混）、mono、front（仅前置左右）、lfe（仅低频），
+    /// 或逗号分隔的扬声器名，如 `FL,FR`、`FC`（可选 FL FR FC LFE BL BR FLC FRC BC SL SR）
+    #[arg(long, value_name = "MODE", default_value = "stereo")]
+    pub channel_map: ChannelMode,
+
+    /// 相邻两次频谱分析之间的帧数（跳跃步长），决定分析速率与窗口重叠；
+    /// 大于分析窗口时不重叠，多出的采样被跳过
+    #[arg(
+        long,
+        value_name = "FRAMES",
+        default_value_t = 512,
+        value_parser = clap::value_parser!(u32).range(1..=32768)
+    )]
+    pub hop: u32,
+
+    /// FFT窗函数：rectangular、hann、hamming、blackman-harris、flat-top、kaiser 或 kaiser=β（0 ≤ β ≤ 50）
+    #[arg(long, value_name = "WINDOW", default_value = "hann")]
+    pub window: WindowKind,
+
+    /// 窗函数校正方式：amplitude（保持单频幅度）或 energy（保持宽带能量）
+    #[arg(long, value_name = "CORRECTION", default_value = "energy")]
+    pub window_correction: WindowCorrection,
+
+    /// FFT点数（512–32768），越大频率分辨率越高、时间响应越慢
+    #[arg(
+        long,
+        value_name = "SIZE",
+        default_value_t = 4096,
+        value_parser = clap::value_parser!(u32).range(512..=32768)
+    )]
+    pub fft_size: u32,
+
+    /// 频段（柱状图）数量（8–512），倍频程刻度下由频率范围决定
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 64,
+        value_parser = clap::value_parser!(u32).range(8..=512)
+    )]
+    pub bands: u32,
+
+    /// 频段刻度：log、mel、bark、erb、linear，或ISO中心频率的倍频程 octave[=1|3|6|12]
+    #[arg(long, value_name = "SCALE", default_value = "log")]
+    pub scale: BandScale,
+
+    /// 最低频段的下限频率（Hz）
+    #[arg(long, value_name = "HZ", default_value_t = 20.0)]
+    pub min_freq: f32,
+
+    /// 最高频段的上限频率（Hz），不应超过采样率的一半
+    #[arg(long, value_name = "HZ", default_value_t = 20000.0)]
+    pub max_freq: f32,
+
+    /// 频率计权：a、c、z（不计权）、itu-468，或以1 kHz为基准的倾斜 pink[=dB/oct]（默认每倍频程+3dB）
+    #[arg(long, value_name = "CURVE", default_value = "pink")]
+    pub weighting: Weighting,
+
+    /// 频段滤波器：rectangular（整数bin求平均）、triangular 或 gaussian（小数bin加权插值）
+    #[arg(long, value_name = "FILTER", default_value = "rectangular")]
+    pub band_filter: BandFilter,
+
+    /// 分析方式：fft（单一FFT）、cqt（常Q变换，每个半音分辨率相同，建议配合较大的 --fft-size）
+    /// 或 multires（多分辨率FFT，低频用长窗、高频用短窗）
+    #[arg(long, value_name = "MODE", default_value = "fft")]
+    pub analyzer: AnalyzerMode,
+
+    /// 常Q模式每倍频程的频点数，频段数由 --min-freq/--max-freq 决定
+    #[arg(
+        long,
+        value_name = "BINS",
+        default_value_t = 12,
+        value_parser = clap::value_parser!(u32).range(1..=48)
+    )]
+    pub bins_per_octave: u32,
+
+    /// 常Q模式的带宽偏移（Hz）：0为常Q，大于0时为可变Q，低频时间响应更快
+    #[arg(long, value_name = "HZ", default_value_t = 0.0)]
+    pub cqt_gamma: f32,
+
+    /// 多分辨率模式的各级FFT点数（逗号分隔），从低频到高频
+    #[arg(
+        long,
+        value_name = "SIZES",
+        value_delimiter = ',',
+        default_values_t = [16384, 4096, 1024],
+        value_parser = clap::value_parser!(u32).range(512..=32768)
+    )]
+    pub resolutions: Vec<u32>,
+
+    /// 多分辨率模式相邻两级之间的分频点（Hz，逗号分隔），个数比 --resolutions 少一个
+    #[arg(
+        long,
+        value_name = "HZ",
+        value_delimiter = ',',
+        default_values_t = [250.0, 2500.0]
+    )]
+    pub crossovers: Vec<f32>,
+
+    /// 频段上升时的平滑时间常数（毫秒），0为不平滑
+    #[arg(long, value_name = "MS", default_value_t = 550.0)]
+    pub attack: f32,
+
+    /// 频段回落时的平滑时间常数（毫秒），0为不平滑
+    #[arg(long, value_name = "MS", default_value_t = 550.0)]
+    pub release: f32,
+
+    /// 按频段覆盖平滑时间常数，格式为 频段[-频段]:起音ms:释放ms，如 `0-7:5:400`，可重复指定
+    #[arg(long, value_name = "BANDS:ATTACK:RELEASE", value_delimiter = ',')]
+    pub band_smoothing: Vec<BandSmoothing>,
+
+    /// 归一化方式：percentile（每帧按95百分位数）、agc（长时间自动增益）或 fixed-db（固定dB范围）
+    #[arg(long, value_name = "MODE", default_value = "percentile")]
+    pub normalize: NormalizeMode,
+
+    /// 自动增益在电平升高时的跟随时间常数（毫秒）
+    #[arg(long, value_name = "MS", default_value_t = 300.0)]
+    pub agc_attack: f32,
+
+    /// 自动增益在电平降低时的回落时间常数（毫秒）
+    #[arg(long, value_name = "MS", default_value_t = 8000.0)]
+    pub agc_decay: f32,
+
+    /// 自动增益下限（dB）
+    #[arg(long, value_name = "DB", default_value_t = -12.0, allow_negative_numbers = true)]
+    pub agc_min_gain: f32,
+
+    /// 自动增益上限（dB）
+    #[arg(
+        long,
+        value_name = "DB",
+        default_value_t = 40.0,
+        allow_negative_numbers = true
+    )]
+    pub agc_max_gain: f32,
+
+    /// 自动增益按频段独立计算，而不是所有频段共用一个增益
+    #[arg(long)]
+    pub agc_per_band: bool,
+
+    /// 固定dB模式的下限（dBFS），对应柱高为0
+    #[arg(long, value_name = "DB", default_value_t = -70.0, allow_negative_numbers = true)]
+    pub floor_db: f32,
+
+    /// 固定dB模式的上限（dBFS），对应满柱高
+    #[arg(long, value_name = "DB", default_value_t = -10.0, allow_negative_numbers = true)]
+    pub ceiling_db: f32,
+
+    /// 起音检测阈值 = 近1秒谱通量均值 + 该倍数 × 标准差，越小越灵敏
+    #[arg(long, value_name = "K", default_value_t = 1.5)]
+    pub onset_sensitivity: f32,
+
+    /// 同一频带两次起音之间的最小间隔（毫秒）
+    #[arg(long, value_name = "MS", default_value_t = 100.0)]
+    pub onset_min_interval: f32,
+
+    /// 速度估计的下限（BPM），不低于15：速度历史需覆盖两个节拍周期
+    #[arg(long, value_name = "BPM", default_value_t = 60.0)]
+    pub min_bpm: f32,
+
+    /// 速度估计的上限（BPM）
+    #[arg(long, value_name = "BPM", default_value_t = 180.0)]
+    pub max_bpm: f32,
+
+    /// 加载命名的频段校准档案，作为各频段的增益
+    #[arg(long, value_name = "NAME", conflicts_with = "calibrate")]
+    pub profile: Option<String>,
+
+    /// 校准模式：播放粉红噪声或较长的音乐片段，测量结束后把使各频段电平一致的增益保存为命名档案
+    #[arg(long, value_name = "NAME")]
+    pub calibrate: Option<String>,
+
+    /// 校准时长（秒）
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 30.0,
+        requires = "calibrate"
+    )]
+    pub calibrate_secs: f32,
+
+    /// 左右声道分开显示：上方柱状图为左声道，下方镜像柱状图为右声道
+    #[arg(long)]
+    pub split_stereo: bool,
+
+    /// 在窗口顶部显示EBU R128响度表，并在标题栏显示各项读数；未启用时不测量响度
+    #[arg(long)]
+    pub loudness_meter: bool,
+
+    /// 在每个频谱柱外侧显示峰值保持帽
+    #[arg(long)]
+    pub peak_caps: bool,
+
+    /// 峰值帽被顶起后的保持时间（毫秒）
+    #[arg(long, value_name = "MS", default_value_t = 500.0)]
+    pub peak_hold: f32,
+
+    /// 峰值帽的下落加速度（满柱高/秒²）
+    #[arg(long, value_name = "ACCEL", default_value_t = 4.0)]
+    pub peak_gravity: f32,
+}
+
+/// 原始PCM采样格式，命名与ffmpeg/sox一致
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PcmFormat {
+    F32le, // 32位浮点，小端序
+    S16le, // 16位有符号整数，小端序
+    S24le, // 24位有符号整数（紧凑排列），小端序
+    S32le, // 32位有符号整数，小端序
+}
+
+impl PcmFormat {
+    /// 对应的采样数据类型
+    pub fn sample_type(self) -> SampleType {
+        match self {
+            PcmFormat::F32le => SampleType::F32,
+            PcmFormat::S16le => SampleType::I16,
+            PcmFormat::S24le => SampleType::I24,
+            PcmFormat::S32le => SampleType::I32,
+        }
+    }
+}
+
+impl Config {
+    /// 频谱分析器设置
+    pub fn analyzer_settings(&self) -> AnalyzerSettings {
+        AnalyzerSettings {
+            window: self.window,
+            window_correction: self.window_correction,
+            fft_size: self.fft_size as usize,
+            bands: self.bands as usize,
+            scale: self.scale,
+            min_freq: self.min_freq,
+            max_freq: self.max_freq,
+            band_filter: self.band_filter,
+            mode: self.analyzer,
+            bins_per_octave: self.bins_per_octave,
+            cqt_gamma: self.cqt_gamma,
+            resolutions: self.resolutions.iter().map(|&size| size as usize).collect(),
+            crossovers: self.crossovers.clone(),
+            hop: self.hop as usize,
+            smoothing: Smoothing {
+                attack_ms: self.attack.max(0.0),
+                release_ms: self.release.max(0.0),
+                overrides: self.band_smoothing.clone(),
+            },
+            normalize: NormalizeSettings {
+                mode: self.normalize,
+                agc_attack_ms: self.agc_attack.max(0.0),
+                agc_decay_ms: self.agc_decay.max(0.0),
+                min_gain_db: self.agc_min_gain,
+                max_gain_db: self.agc_max_gain,
+                agc_per_band: self.agc_per_band,
+                floor_db: self.floor_db,
+                ceiling_db: self.ceiling_db,
+            },
+            weighting: self.weighting,
+            onset: OnsetSettings {
+                sensitivity: self.onset_sensitivity.max(0.0),
+                min_interval_ms: self.onset_min_interval.max(0.0),
+            },
+            tempo: TempoSettings {
+                min_bpm: self.min_bpm,
+                max_bpm: self.max_bpm,
+            },
+        }
+    }
+
+    /// 峰值帽设置，未启用峰值帽时为None
+    pub fn peak_settings(&self) -> Option<PeakSettings> {
+        self.peak_caps.then(|| PeakSettings {
+            hold_ms: self.peak_hold.max(0.0),
+            gravity: self.peak_gravity.max(0.0),
+        })
+    }
+
+    /// 检查单个参数无法表达的约束，不满足时打印用法错误并退出
+    pub fn validate(&self) {
+        let mut cmd = Self::command();
+        if !(self.min_freq > 0.0 && self.min_freq < self.max_freq) {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "频率范围无效：需要 0 < --min-freq < --max-freq",
+            )
+            .exit();
+        }
+        // 标准输入的采样率已知，频段不能超过奈奎斯特频率
+        if self.stdin && self.max_freq > self.rate as f32 / 2.0 {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "--max-freq {} Hz 超过采样率 {} Hz 的奈奎斯特频率 {} Hz",
+                    self.max_freq,
+                    self.rate,
+                    self.rate as f32 / 2.0
+                ),
+            )
+            .exit();
+        }
+        if self.crossovers.len() + 1 != self.resolutions.len() {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "--crossovers 的个数必须比 --resolutions 少一个",
+            )
+            .exit();
+        }
+        if !self.crossovers.windows(2).all(|pair| pair[0] < pair[1]) {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "--crossovers 必须按从低到高排列",
+            )
+            .exit();
+        }
+        if self.agc_min_gain > self.agc_max_gain {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "--agc-min-gain 不能大于 --agc-max-gain",
+            )
+            .exit();
+        }
+        if self.floor_db >= self.ceiling_db {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "--floor-db 必须小于 --ceiling-db",
+            )
+            .exit();
+        }
+        if !(self.calibrate_secs.is_finite() && self.calibrate_secs > 0.0) {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "--calibrate-secs 必须是大于0的有限值",
+            )
+            .exit();
+        }
+        if !(self.min_bpm >= tempo::MIN_BPM && self.min_bpm < self.max_bpm) {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "速度范围必须满足 {} <= --min-bpm < --max-bpm",
+                    tempo::MIN_BPM
+                ),
+            )
+            .exit();
+        }
+        let band_count = self.analyzer_settings().band_count();
+        if band_count == 0 {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                "频率范围内没有完整的倍频程频段，请扩大 --min-freq/--max-freq 范围",
+            )
+            .exit();
+        }
+        if band_count > 512 {
+            cmd.error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "频段数 {} 超过上限512，请减小频率范围或每倍频程频点数",
+                    band_count
+                ),
+            )
+            .exit();
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! 频段校准模块
+//!
+//! 校准时播放粉红噪声或较长的音乐片段，[`Calibrator`] 累积各频段（计权后）的长期平均功率，
+//! 求出使各频段平均电平一致的增益。增益按频段中心频率保存为命名档案，
+//! 启动时加载档案并按当前频段的中心频率插值，频段数或刻度改变后仍可使用
+
+use anyhow::{Context, Result, bail};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAX_GAIN_DB: f32 = 24.0; // 校准增益的上下限（dB），避免近乎无声的频段被无限放大
+
+/// 累积各频段长期平均功率的校准器
+pub struct Calibrator {
+    power: Vec<f64>, // 各频段累积的功率
+    windows: usize,  // 已累积的分析次数
+    target: usize,   // 需要累积的分析次数
+}
+
+impl Calibrator {
+    /// 为 `bands` 个频段创建校准器，累积 `target` 次分析后完成
+    pub fn new(bands: usize, target: usize) -> Self {
+        Self {
+            power: vec![0.0; bands],
+            windows: 0,
+            target: target.max(1),
+        }
+    }
+
+    /// 累积一次分析的频段幅值（各声道依次排列），达到目标次数时返回 `true`
+    pub fn add(&mut self, levels: &[f32]) -> bool {
+        let bands = self.power.len();
+        for (i, &level) in levels.iter().enumerate() {
+            self.power[i % bands] += (level as f64).powi(2);
+        }
+        self.windows += 1;
+        self.windows >= self.target
+    }
+
+    /// 使各频段平均电平等于所有频段几何平均电平的增益（dB）
+    pub fn gains_db(&self) -> Vec<f32> {
+        let levels_db: Vec<Option<f64>> = self
+            .power
+            .iter()
+            .map(|&p| (p > 0.0).then(|| 10.0 * (p / self.windows as f64).log10()))
+            .collect();
+        let measured: Vec<f64> = levels_db.iter().flatten().copied().collect();
+        if measured.is_empty() {
+            return vec![0.0; self.power.len()];
+        }
+        let reference = measured.iter().sum::<f64>() / measured.len() as f64;
+        levels_db
+            .iter()
+            .map(|level| {
+                // 没有能量的频段不做调整
+                level.map_or(0.0, |level| {
+                    ((reference - level) as f32).clamp(-MAX_GAIN_DB, MAX_GAIN_DB)
+                })
+            })
+            .collect()
+    }
+}
+
+/// 校准档案：各频段中心频率及其增益
+pub struct Profile {
+    points: Vec<(f32, f32)>, // (中心频率Hz, 增益dB)，按频率升序排列
+}
+
+impl Profile {
+    /// 由频段边界频率和对应增益（dB）创建档案
+    pub fn new(edges: &[(f32, f32)], gains_db: &[f32]) -> Self {
+        let points = edges
+            .iter()
+            .zip(gains_db)
+            .map(|(&(low, high), &gain)| ((low * high).sqrt(), gain))
+            .collect();
+        Self { points }
+    }
+
+    /// 读取命名档案
+    pub fn load(name: &str) -> Result<Self> {
+        Self::read(&profile_path(name)?)
+    }
+
+    /// 保存为命名档案，返回文件路径
+    pub fn save(&self, name: &str) -> Result<PathBuf> {
+        let path = profile_path(name)?;
+        self.write(&path)?;
+        Ok(path)
+    }
+
+    /// 从指定路径读取档案
+    fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("无法读取校准档案 {}", path.display()))?;
+        let mut points = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace().map(str::parse::<f32>);
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(Ok(freq)), Some(Ok(gain)), None) if freq > 0.0 && gain.is_finite() => {
+                    points.push((freq, gain))
+                }
+                _ => bail!("{} 第{}行格式错误: `{}`", path.display(), number + 1, line),
+            }
+        }
+        if points.is_empty() {
+            bail!("校准档案 {} 中没有数据", path.display());
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self { points })
+    }
+
+    /// 把档案写入指定路径，必要时创建所在目录
+    fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("无法创建目录 {}", dir.display()))?;
+        }
+        let mut text = String::from("# 频段校准档案：中心频率(Hz) 增益(dB)\n");
+        for &(freq, gain) in &self.points {
+            let _ = writeln!(text, "{:.3} {:.3}", freq, gain);
+        }
+        fs::write(path, text).with_context(|| format!("无法写入 {}", path.display()))
+    }
+
+    /// 按当前各频段的中心频率在对数频率上线性插值，返回幅度增益
+    pub fn band_gains(&self, edges: &[(f32, f32)]) -> Vec<f32> {
+        edges
+            .iter()
+            .map(|&(low, high)| {
+                let freq = (low * high).sqrt();
+                let upper = self.points.partition_point(|&(f, _)| f < freq);
+                // 超出档案范围时取最近端点的增益
+                let gain_db = if upper == 0 {
+                    self.points[0].1
+                } else if upper == self.points.len() {
+                    self.points[upper - 1].1
+                } else {
+                    let (f0, g0) = self.points[upper - 1];
+                    let (f1, g1) = self.points[upper];
+                    g0 + (g1 - g0) * (freq / f0).ln() / (f1 / f0).ln()
+                };
+                10_f32.powf(gain_db / 20.0)
+            })
+            .collect()
+    }
+}
+
+/// 命名档案的保存路径：`<配置目录>/zenlesszonezero-music-visualizer/profiles/<名称>.txt`
+fn profile_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("校准档案名 `{}` 只能包含字母、数字、`-` 和 `_`", name);
+    }
+    // Windows使用 %APPDATA%，其他系统遵循XDG规范
+    let base = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .context("无法确定配置目录（未设置 APPDATA、XDG_CONFIG_HOME 或 HOME）")?;
+    Ok(base
+        .join(env!("CARGO_PKG_NAME"))
+        .join("profiles")
+        .join(format!("{}.txt", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 中心频率依次为100 Hz、1 kHz、10 kHz的频段
+    fn decade_edges() -> Vec<(f32, f32)> {
+        [100.0f32, 1000.0, 10000.0]
+            .iter()
+            .map(|&center| (center / 2.0, center * 2.0))
+            .collect()
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let profile = Profile::new(&decade_edges(), &[3.0, -1.5, 0.25]);
+        let path = std::env::temp_dir()
+            .join(format!("profile-{}", std::process::id()))
+            .join("round-trip.txt");
+        profile.write(&path).unwrap();
+        let loaded = Profile::read(&path).unwrap();
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(loaded.points.len(), profile.points.len());
+        for (&(f0, g0), &(f1, g1)) in loaded.points.iter().zip(&profile.points) {
+            assert!((f0 - f1).abs() < 1e-3 && (g0 - g1).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn band_gains_interpolate_in_log_frequency_and_clamp_at_ends() {
+        let profile = Profile::new(&decade_edges(), &[0.0, 6.0, -6.0]);
+        let to_db = |gain: f32| 20.0 * gain.log10();
+        // 中心频率：低于档案范围、100 Hz与1 kHz的几何中点、1 kHz与10 kHz的几何中点、高于档案范围
+        let edges = [
+            (10.0, 40.0),
+            (200.0, 500.0),
+            (2000.0, 5000.0),
+            (20000.0, 40000.0),
+        ];
+        let gains: Vec<f32> = profile.band_gains(&edges).into_iter().map(to_db).collect();
+        let expected = [0.0, 3.0, 0.0, -6.0];
+        for (gain, expected) in gains.iter().zip(expected) {
+            assert!((gain - expected).abs() < 1e-3, "{:?}", gains);
+        }
+    }
+}
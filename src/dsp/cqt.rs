@@ -0,0 +1,140 @@
+//! 常Q变换模块
+//!
+//! 采用频域核方法（Brown & Puckette）：每个常Q频点的时域核是
+//! 按中心频率调制的汉宁窗，窗长与带宽成反比，预先做FFT得到稀疏的频域核。
+//! 分析时只需对一次FFT的结果与各频域核做内积，每个频点的相对分辨率相同；
+//! `gamma` 大于0时低频带宽加宽为 Δf + γ，即可变Q变换，缩短低频的时间响应
+
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+use std::f32::consts::PI;
+
+const KERNEL_THRESHOLD: f32 = 0.01; // 频域核中小于峰值1%的系数视为零
+
+/// 预计算的常Q频域核
+///
+/// 所有频点的稀疏核依次存放在同一个数组中，`process` 不做堆分配
+pub struct ConstantQ {
+    ranges: Vec<(usize, usize)>, // 每个频点的频域核覆盖的FFT bin范围 [start, end)
+    offsets: Vec<usize>,         // 每个频点的核系数在 `kernels` 中的起始位置
+    kernels: Vec<Complex<f32>>,  // 频域核系数（已取共轭）
+}
+
+impl ConstantQ {
+    /// 按频段边界频率构建常Q频域核
+    ///
+    /// 频点中心取频段的几何中心，带宽取频段宽度；
+    /// 低频窗长超过 `fft_size` 时截断为 `fft_size` 并打印警告，此时该频点的Q值相应降低
+    pub fn new(edges: &[(f32, f32)], gamma: f32, sample_rate: u32, fft_size: usize) -> Self {
+        let fs = sample_rate as f32;
+        let fft = FftPlanner::new().plan_fft_forward(fft_size);
+        let mut temporal = vec![Complex::new(0.0, 0.0); fft_size];
+        let mut cqt = Self {
+            ranges: Vec::with_capacity(edges.len()),
+            offsets: Vec::with_capacity(edges.len()),
+            kernels: Vec::new(),
+        };
+        let mut truncated = 0; // 窗长被截断的频点数
+        let mut longest = 0; // 未截断时最长的窗长
+        for &(freq_start, freq_end) in edges {
+            let center = (freq_start * freq_end).sqrt();
+            let bandwidth = (freq_end - freq_start) + gamma.max(0.0);
+            let wanted = ((fs / bandwidth) as usize).max(1);
+            if wanted > fft_size {
+                truncated += 1;
+                longest = longest.max(wanted);
+            }
+            let len = wanted.min(fft_size);
+
+            // 时域核：居中放置、按窗函数和归一化的调制汉宁窗，
+            // 使幅度为A的正弦信号在该频点得到 A × fft_size / 2，与FFT频段的量纲一致
+            temporal.fill(Complex::new(0.0, 0.0));
+            let start = (fft_size - len) / 2;
+            let hann = |n: usize| 0.5 - 0.5 * (2.0 * PI * (n as f32 + 0.5) / len as f32).cos();
+            let window_sum: f32 = (0..len).map(hann).sum();
+            for n in 0..len {
+                let phase = 2.0 * PI * center * (start + n) as f32 / fs;
+                temporal[start + n] = Complex::from_polar(hann(n) / window_sum, phase);
+            }
+            fft.process(&mut temporal);
+
+            // 只保留正频率部分中大于阈值的连续区间
+            let spectrum = &temporal[..fft_size / 2];
+            let peak = spectrum.iter().map(|c| c.norm()).fold(0.0, f32::max);
+            let threshold = peak * KERNEL_THRESHOLD;
+            let first = spectrum.iter().position(|c| c.norm() >= threshold);
+            let last = spectrum.iter().rposition(|c| c.norm() >= threshold);
+            let (first, last) = match (first, last) {
+                (Some(first), Some(last)) => (first, last + 1),
+                _ => (0, 0),
+            };
+            cqt.offsets.push(cqt.kernels.len());
+            cqt.ranges.push((first, last));
+            cqt.kernels
+                .extend(spectrum[first..last].iter().map(|c| c.conj()));
+        }
+        if truncated > 0 {
+            eprintln!(
+                "常Q模式下最低的 {} 个频点窗长超过 --fft-size {}，Q值降低；需要 {} 点才能保持常Q，可增大 --fft-size 或提高 --min-freq",
+                truncated,
+                fft_size,
+                longest.next_power_of_two()
+            );
+        }
+        cqt
+    }
+
+    /// 计算各常Q频点的幅值，写入 `out`
+    ///
+    /// `spectrum` 为未加窗信号的FFT正频率部分
+    pub fn process(&self, spectrum: &[Complex<f32>], out: &mut [f32]) {
+        for (i, band) in out.iter_mut().enumerate() {
+            let (start_idx, end_idx) = self.ranges[i];
+            let kernel = &self.kernels[self.offsets[i]..][..end_idx - start_idx];
+            let sum: Complex<f32> = spectrum[start_idx..end_idx]
+                .iter()
+                .zip(kernel)
+                .map(|(bin, k)| bin * k)
+                .sum();
+            *band = sum.norm();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::scale::constant_q_edges;
+
+    const RATE: u32 = 48000;
+    const FFT_SIZE: usize = 16384;
+
+    #[test]
+    fn sine_lands_in_its_bin() {
+        // 55 Hz起每半音一个频点，最低频点的窗长约15100点，不被截断
+        let edges = constant_q_edges(12, 55.0, 8000.0);
+        let cqt = ConstantQ::new(&edges, 0.0, RATE, FFT_SIZE);
+        let fft = FftPlanner::new().plan_fft_forward(FFT_SIZE);
+        let mut out = vec![0.0; edges.len()];
+        // 110 Hz、440 Hz、3520 Hz分别是第12、36、72个频点的中心
+        for (freq, bin) in [(110.0, 12), (440.0, 36), (3520.0, 72)] {
+            let mut spectrum: Vec<Complex<f32>> = (0..FFT_SIZE)
+                .map(|i| Complex::new((2.0 * PI * freq * i as f32 / RATE as f32).sin(), 0.0))
+                .collect();
+            fft.process(&mut spectrum);
+            cqt.process(&spectrum[..FFT_SIZE / 2], &mut out);
+            let peak = (0..out.len())
+                .max_by(|&a, &b| out[a].total_cmp(&out[b]))
+                .unwrap();
+            assert_eq!(peak, bin, "{} Hz", freq);
+            // 满幅正弦的幅值与FFT频段一致，约为 fft_size / 2
+            let expected = FFT_SIZE as f32 / 2.0;
+            assert!(
+                (out[bin] / expected - 1.0).abs() < 0.05,
+                "{} Hz: {}",
+                freq,
+                out[bin]
+            );
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! 包络平滑模块
+//!
+//! 对每个频段做一阶起音/释放平滑：数值上升时按起音时间常数跟随，
+//! 下降时按释放时间常数回落。系数由分析间隔（跳跃步长 / 采样率）换算，
+//! 与渲染帧率无关；可以为部分频段单独指定时间常数
+
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// 平滑设置
+#[derive(Clone, Debug)]
+pub struct Smoothing {
+    pub attack_ms: f32,                // 起音时间常数（毫秒）
+    pub release_ms: f32,               // 释放时间常数（毫秒）
+    pub overrides: Vec<BandSmoothing>, // 按频段覆盖的时间常数，后出现的优先
+}
+
+/// 一组频段的平滑时间常数
+#[derive(Clone, Debug)]
+pub struct BandSmoothing {
+    pub bands: RangeInclusive<usize>, // 频段下标范围
+    pub attack_ms: f32,               // 起音时间常数（毫秒）
+    pub release_ms: f32,              // 释放时间常数（毫秒）
+}
+
+impl FromStr for BandSmoothing {
+    type Err = String;
+
+    /// 解析 `频段:起音:释放`，频段为单个下标或 `起-止` 范围，如 `0-7:5:400`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "无效的频段平滑设置 `{}`，格式为 频段[-频段]:起音ms:释放ms",
+                s
+            )
+        };
+        let mut parts = s.split(':');
+        let (Some(bands), Some(attack), Some(release), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let index = |v: &str| v.trim().parse::<usize>().map_err(|_| invalid());
+        let time = |v: &str| {
+            v.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|ms| ms.is_finite() && *ms >= 0.0)
+                .ok_or_else(invalid)
+        };
+        let bands = match bands.split_once('-') {
+            Some((first, last)) => index(first)?..=index(last)?,
+            None => index(bands)?..=index(bands)?,
+        };
+        // 起始下标大于结束下标的范围为空，覆盖不会生效
+        if bands.is_empty() {
+            return Err(invalid());
+        }
+        Ok(BandSmoothing {
+            bands,
+            attack_ms: time(attack)?,
+            release_ms: time(release)?,
+        })
+    }
+}
+
+/// 一阶平滑系数 α = 1 - e^(-Δt/τ)：`interval` 为Δt（秒），`ms` 为时间常数τ（毫秒），
+/// τ为0时直接跟随
+pub(crate) fn one_pole_coefficient(interval: f32, ms: f32) -> f32 {
+    if ms <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-interval * 1000.0 / ms).exp()
+    }
+}
+
+/// 各频段的起音/释放包络
+pub struct Envelope {
+    smoothing: Smoothing, // 平滑设置，分析间隔变化时据此重算系数
+    attack: Vec<f32>,     // 每个频段的起音系数
+    release: Vec<f32>,    // 每个频段的释放系数
+    state: Vec<f32>,      // 各声道各频段的平滑值，按声道依次排列
+}
+
+impl Envelope {
+    /// 为 `bands` 个频段、`channels` 个声道创建包络，`interval` 为相邻两次分析的间隔（秒）
+    pub fn new(smoothing: &Smoothing, bands: usize, channels: usize, interval: f32) -> Self {
+        let mut envelope = Self {
+            smoothing: smoothing.clone(),
+            attack: vec![1.0; bands],
+            release: vec![1.0; bands],
+            state: vec![0.0; bands * channels],
+        };
+        envelope.set_interval(interval);
+        envelope
+    }
+
+    /// 按新的分析间隔（秒）重算各频段系数
+    pub fn set_interval(&mut self, interval: f32) {
+        for (i, (attack, release)) in self.attack.iter_mut().zip(&mut self.release).enumerate() {
+            let (attack_ms, release_ms) = self
+                .smoothing
+                .overrides
+                .iter()
+                .rev()
+                .find(|o| o.bands.contains(&i))
+                .map_or((self.smoothing.attack_ms, self.smoothing.release_ms), |o| {
+                    (o.attack_ms, o.release_ms)
+                });
+            *attack = one_pole_coefficient(interval, attack_ms);
+            *release = one_pole_coefficient(interval, release_ms);
+        }
+    }
+
+    /// 平滑 `values`（布局与 `state` 相同），结果写回 `values`
+    pub fn process(&mut self, values: &mut [f32]) {
+        let bands = self.attack.len();
+        for (i, (value, state)) in values.iter_mut().zip(&mut self.state).enumerate() {
+            let band = i % bands;
+            let alpha = if *value > *state {
+                self.attack[band]
+            } else {
+                self.release[band]
+            };
+            *state += (*value - *state) * alpha;
+            *value = *state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_band_ranges() {
+        let single: BandSmoothing = "3:5:400".parse().unwrap();
+        assert_eq!(single.bands, 3..=3);
+        let range: BandSmoothing = "0-7:5:400".parse().unwrap();
+        assert_eq!(range.bands, 0..=7);
+        assert_eq!((range.attack_ms, range.release_ms), (5.0, 400.0));
+        for invalid in ["7-0:5:400", "0-7:5", "0-7:-1:400", "a:5:400"] {
+            assert!(invalid.parse::<BandSmoothing>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn step_response_follows_time_constants() {
+        // 分析间隔10 ms，起音时间常数100 ms、释放时间常数1 s，频段1覆盖为直接跟随
+        let smoothing = Smoothing {
+            attack_ms: 100.0,
+            release_ms: 1000.0,
+            overrides: vec!["1:0:0".parse().unwrap()],
+        };
+        let mut envelope = Envelope::new(&smoothing, 2, 1, 0.01);
+        // 阶跃上升：经过一个时间常数达到 1 - 1/e
+        let mut values = [0.0; 2];
+        for _ in 0..10 {
+            values = [1.0; 2];
+            envelope.process(&mut values);
+        }
+        assert!((values[0] - (1.0 - (-1.0f32).exp())).abs() < 1e-4);
+        assert_eq!(values[1], 1.0);
+        // 阶跃下降：经过一个时间常数回落到起点的 1/e
+        let start = values[0];
+        for _ in 0..100 {
+            values = [0.0; 2];
+            envelope.process(&mut values);
+        }
+        assert!((values[0] - start * (-1.0f32).exp()).abs() < 1e-4);
+        assert_eq!(values[1], 0.0);
+    }
+}
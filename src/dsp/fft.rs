@@ -1,157 +1,712 @@
-use crate::dsp::spectrum::SharedPipe;
-use once_cell::sync::Lazy;
-use rustfft::{Fft, num_complex::Complex};
-use std::sync::Mutex;
-
-const FFT_SIZE: usize = 4096;
-const BANDS: usize = 64;
-
-static BAND_INDEX_CACHE: Lazy<Mutex<Vec<(usize, usize)>>> = Lazy::new(|| Mutex::new(Vec::new()));
-static BAND_GAINS_CACHE: Lazy<Mutex<Vec<f32>>> = Lazy::new(|| Mutex::new(Vec::new()));
-#[inline(always)]
-fn compute_magnitudes(spectrum: &[Complex<f32>], start_idx: usize, end_idx: usize) -> f32 {
-    let mut sum_squares = 0.0f32;
-    let iter_end = start_idx + ((end_idx - start_idx) / 4) * 4;
-    for i in (start_idx..iter_end).step_by(4) {
-        let mag_sq1 = spectrum[i].re * spectrum[i].re + spectrum[i].im * spectrum[i].im;
-        let mag_sq2 =
-            spectrum[i + 1].re * spectrum[i + 1].re + spectrum[i + 1].im * spectrum[i + 1].im;
-        let mag_sq3 =
-            spectrum[i + 2].re * spectrum[i + 2].re + spectrum[i + 2].im * spectrum[i + 2].im;
-        let mag_sq4 =
-            spectrum[i + 3].re * spectrum[i + 3].re + spectrum[i + 3].im * spectrum[i + 3].im;
-        sum_squares += mag_sq1 + mag_sq2 + mag_sq3 + mag_sq4;
-    }
-    for i in iter_end..end_idx {
-        let mag_sq = spectrum[i].re * spectrum[i].re + spectrum[i].im * spectrum[i].im;
-        sum_squares += mag_sq;
-    }
-    sum_squares
+//! 频谱分析模块
+//!
+//! [`SpectrumAnalyzer`] 持有FFT计划、窗函数、频段表与全部中间缓冲区，
+//! 每次 `process` 只在预分配的缓冲区上计算，不做任何堆分配；
+//! 不同设置的分析器可以同时存在。
+//! FFT模式用频段滤波器组汇总各频段，常Q模式对同一次FFT的结果应用常Q频域核，
+//! 多分辨率模式在同一滑动窗口上运行多种点数的FFT，低频取长窗、高频取短窗
+
+use crate::dsp::cqt::ConstantQ;
+use crate::dsp::envelope::{Envelope, Smoothing};
+use crate::dsp::filterbank::{BandFilter, Filterbank};
+use crate::dsp::loudness::LoudnessState;
+use crate::dsp::normalize::{NormalizeSettings, Normalizer};
+use crate::dsp::onset::{OnsetBand, OnsetDetector, OnsetSettings};
+use crate::dsp::scale::{self, BandScale};
+use crate::dsp::spectrum::SpectrumFrame;
+use crate::dsp::tempo::{TempoSettings, TempoTracker};
+use crate::dsp::weighting::Weighting;
+use crate::dsp::window::{self, WindowCorrection, WindowKind};
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// 分析方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnalyzerMode {
+    Fft,      // 单一FFT + 频段滤波器组
+    Cqt,      // 常Q（或可变Q）变换，每倍频程分辨率相同
+    MultiRes, // 多分辨率FFT，按分频点为各频段选择FFT点数
+}
+
+impl FromStr for AnalyzerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fft" => Ok(AnalyzerMode::Fft),
+            "cqt" => Ok(AnalyzerMode::Cqt),
+            "multires" => Ok(AnalyzerMode::MultiRes),
+            _ => Err(format!("未知的分析方式 `{}`，可选: fft, cqt, multires", s)),
+        }
+    }
 }
-fn init_band_indices_cache() {
-    let mut cache = BAND_INDEX_CACHE.lock().unwrap();
-    if !cache.is_empty() {
-        return;
-    }
-    let sample_rate = 48000.0;
-    let freq_resolution = sample_rate / FFT_SIZE as f32;
-    let min_freq: f32 = 20.0;
-    let max_freq: f32 = 20000.0;
-    let log_min = min_freq.log10();
-    let log_max = max_freq.log10();
-    let log_range = log_max - log_min;
-    for i in 0..BANDS {
-        let log_pos = log_min + log_range * (i as f32 / BANDS as f32);
-        let freq_start = 10_f32.powf(log_pos);
-        let log_pos_end = log_min + log_range * ((i + 1) as f32 / BANDS as f32);
-        let freq_end = 10_f32.powf(log_pos_end);
-        let start_idx = (freq_start / freq_resolution) as usize;
-        let end_idx = (freq_end / freq_resolution) as usize;
-        let start_idx = start_idx.max(1).min(FFT_SIZE / 2 - 1);
-        let end_idx = end_idx.max(start_idx + 1).min(FFT_SIZE / 2);
-        cache.push((start_idx, end_idx));
-    }
-    let mut gains_cache = BAND_GAINS_CACHE.lock().unwrap();
-    *gains_cache = vec![1.0; BANDS];
+
+/// 频谱分析器设置
+#[derive(Clone, Debug)]
+pub struct AnalyzerSettings {
+    pub window: WindowKind,                  // 窗函数类型
+    pub window_correction: WindowCorrection, // 窗函数校正方式
+    pub fft_size: usize,                     // FFT点数，决定频率分辨率
+    pub bands: usize,                        // 每个声道输出的频段数（倍频程刻度下忽略）
+    pub scale: BandScale,                    // 频段刻度
+    pub min_freq: f32,                       // 最低频段的下限频率（Hz）
+    pub max_freq: f32,                       // 最高频段的上限频率（Hz）
+    pub band_filter: BandFilter,             // 频段滤波器形状
+    pub mode: AnalyzerMode,                  // 分析方式
+    pub bins_per_octave: u32,                // 常Q模式每倍频程的频点数
+    pub cqt_gamma: f32,                      // 可变Q的带宽偏移（Hz），0为常Q
+    pub resolutions: Vec<usize>,             // 多分辨率模式的各级FFT点数，从低频到高频
+    pub crossovers: Vec<f32>,                // 多分辨率模式相邻两级之间的分频点（Hz）
+    pub hop: usize,                          // 相邻两次分析之间的帧数
+    pub smoothing: Smoothing,                // 频段起音/释放平滑
+    pub normalize: NormalizeSettings,        // 频段归一化方式
+    pub weighting: Weighting,                // 频率计权曲线
+    pub onset: OnsetSettings,                // 起音检测
+    pub tempo: TempoSettings,                // 速度估计
 }
-pub fn run_fft(
-    samples: &mut [f32],
-    fft_input: &mut [Complex<f32>],
-    fft: &dyn Fft<f32>,
-    spectrum_pipe: &SharedPipe,
-) {
-    if BAND_INDEX_CACHE.lock().unwrap().is_empty() {
-        init_band_indices_cache();
-    }
-    let band_index = BAND_INDEX_CACHE.lock().unwrap();
-    let band_gains = BAND_GAINS_CACHE.lock().unwrap();
-    let samples_len = samples.len().min(FFT_SIZE);
-    let mut windowed_samples = vec![0.0f32; samples_len];
-    let chunks = samples_len / 8;
-    for chunk in 0..chunks {
-        let base = chunk * 8;
-        windowed_samples[base] = samples[base];
-        windowed_samples[base + 1] = samples[base + 1];
-        windowed_samples[base + 2] = samples[base + 2];
-        windowed_samples[base + 3] = samples[base + 3];
-        windowed_samples[base + 4] = samples[base + 4];
-        windowed_samples[base + 5] = samples[base + 5];
-        windowed_samples[base + 6] = samples[base + 6];
-        windowed_samples[base + 7] = samples[base + 7];
-    }
-    for i in 0..samples_len {
-        fft_input[i].re = windowed_samples[i];
-        fft_input[i].im = 0.0;
-    }
-    for i in samples_len..FFT_SIZE {
-        fft_input[i].re = 0.0;
-        fft_input[i].im = 0.0;
-    }
-    fft.process(fft_input);
-    let spectrum = &fft_input[..FFT_SIZE / 2];
-    let mut bands = vec![0.0f32; BANDS];
-    {
-        for i in 0..BANDS {
-            let (start_idx, end_idx) = band_index[i];
-            if start_idx >= end_idx {
-                continue;
+
+impl AnalyzerSettings {
+    /// 各频段的边界频率
+    pub fn band_edges(&self) -> Vec<(f32, f32)> {
+        match self.mode {
+            AnalyzerMode::Fft | AnalyzerMode::MultiRes => {
+                scale::band_edges(self.scale, self.min_freq, self.max_freq, self.bands)
             }
-            let sum_squares = compute_magnitudes(spectrum, start_idx, end_idx);
-            let count = (end_idx - start_idx) as f32;
-            if count > 0.0 {
-                bands[i] = (sum_squares / count).sqrt() * band_gains[i];
+            AnalyzerMode::Cqt => {
+                scale::constant_q_edges(self.bins_per_octave, self.min_freq, self.max_freq)
+            }
+        }
+    }
+
+    /// 实际输出的频段数，倍频程刻度和常Q模式由频率范围决定
+    pub fn band_count(&self) -> usize {
+        self.band_edges().len()
+    }
+
+    /// 各分析级的FFT点数及其负责的频段下标范围
+    ///
+    /// 多分辨率模式按频段几何中心与分频点比较，依次分配给各级；
+    /// 没有分到频段的级被省略
+    fn stage_layout(&self, edges: &[(f32, f32)]) -> Vec<(usize, Range<usize>)> {
+        if self.mode != AnalyzerMode::MultiRes {
+            return vec![(self.fft_size, 0..edges.len())];
+        }
+        let mut layout = Vec::with_capacity(self.resolutions.len());
+        let mut start = 0;
+        for (level, &size) in self.resolutions.iter().enumerate() {
+            let upper = self.crossovers.get(level).copied().unwrap_or(f32::INFINITY);
+            let end = start
+                + edges[start..]
+                    .iter()
+                    .take_while(|(low, high)| (low * high).sqrt() < upper)
+                    .count();
+            if end > start {
+                layout.push((size, start..end));
             }
+            start = end;
         }
+        layout
     }
-    apply_band_gain_compensation(&mut bands);
-    improved_normalize_spectrum(&mut bands);
-    spectrum_pipe.write(&bands)
 }
 
-fn apply_band_gain_compensation(bands: &mut [f32]) {
-    let bands_len = bands.len();
-    for (i, band) in bands.iter_mut().enumerate() {
-        let freq_ratio = i as f32 / bands_len as f32; // 归一化频率位置 [0,1]
+/// 频谱分析器
+pub struct SpectrumAnalyzer {
+    stages: Vec<FftStage>,       // 各FFT分辨率的分析级，单一FFT模式只有一级
+    window_frames: usize,        // 每个声道的分析窗口帧数（最大的FFT点数）
+    band_count: usize,           // 每个声道的频段数
+    band_edges: Vec<(f32, f32)>, // 每个频段的边界频率（Hz）
+    settings: AnalyzerSettings,  // 创建时的设置，采样率变化时据此重建频段表
+    channels: usize,             // 分析的声道数（1或2）
+    sample_rate: u32,            // 频段表对应的采样率
+    band_gains: Vec<f32>,        // 每个频段的增益
+    band_weights: Vec<f32>,      // 每个频段中心频率处的计权增益
+    envelope: Envelope,          // 输出前的起音/释放平滑
+    bands: Vec<f32>,             // 各声道频段值，按声道依次排列
+    levels: Vec<f32>,            // 计权后、应用频段增益前的频段幅值，供校准和起音检测使用
+    onset: OnsetDetector,        // 谱通量起音检测
+    tempo: TempoTracker,         // 速度估计与节拍相位跟踪
+    time: f64,                   // 音频流开始以来的秒数
+    normalizer: Normalizer,      // 把频段幅值映射到 [0, 1]
+    frame: SpectrumFrame,        // 输出的频谱帧
+}
+
+impl SpectrumAnalyzer {
+    /// 按设置创建分析器
+    ///
+    /// `channels` 为输入交错采样的声道数，超过2时只分析前两个声道
+    pub fn new(settings: &AnalyzerSettings, sample_rate: u32, channels: usize) -> Self {
+        let band_edges = settings.band_edges();
+        let band_count = band_edges.len();
+        let band_weights = settings.weighting.band_gains(&band_edges);
+        let channels = channels.clamp(1, 2);
+        let stages: Vec<FftStage> = settings
+            .stage_layout(&band_edges)
+            .into_iter()
+            .map(|(size, bands)| FftStage::new(settings, size, bands, &band_edges, sample_rate))
+            .collect();
+        let onset = OnsetDetector::new(
+            &settings.onset,
+            &band_edges,
+            settings.fft_size as f32 / 2.0,
+            settings.hop as f32 / sample_rate as f32,
+        );
+        Self {
+            window_frames: stages.iter().map(|stage| stage.size).max().unwrap_or(0),
+            stages,
+            band_count,
+            band_edges,
+            settings: settings.clone(),
+            channels,
+            sample_rate,
+            band_gains: vec![1.0; band_count],
+            band_weights,
+            envelope: Envelope::new(
+                &settings.smoothing,
+                band_count,
+                channels,
+                settings.hop as f32 / sample_rate as f32,
+            ),
+            bands: vec![0.0; band_count * channels],
+            levels: vec![0.0; band_count * channels],
+            onset,
+            tempo: TempoTracker::new(&settings.tempo, settings.hop as f32 / sample_rate as f32),
+            time: 0.0,
+            // 各级幅值已换算到 `fft_size` 点FFT的量纲，满幅正弦对应 fft_size / 2
+            normalizer: Normalizer::new(
+                &settings.normalize,
+                band_count * channels,
+                settings.fft_size as f32 / 2.0,
+                settings.hop as f32 / sample_rate as f32,
+            ),
+            frame: SpectrumFrame::new(band_count),
+        }
+    }
+
+    /// 各频段的边界频率
+    pub fn band_edges(&self) -> &[(f32, f32)] {
+        &self.band_edges
+    }
+
+    /// 最近一次分析中计权后、应用频段增益前的频段幅值（各声道依次排列）
+    pub fn levels(&self) -> &[f32] {
+        &self.levels
+    }
+
+    /// 设置随后续频谱帧一起发布的响度
+    pub fn set_loudness(&mut self, loudness: LoudnessState) {
+        self.frame.loudness = loudness;
+    }
+
+    /// 设置各频段的幅度增益（例如由校准档案得到）
+    pub fn set_band_gains(&mut self, gains: Vec<f32>) {
+        if gains.len() == self.band_count {
+            self.band_gains = gains;
+        }
+    }
+
+    /// 每次分析需要的交错采样数
+    pub fn window_len(&self) -> usize {
+        self.window_frames * self.channels
+    }
+
+    /// 采样率变化时重新计算频段表
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            for stage in &mut self.stages {
+                stage.rebuild_bank(&self.settings, &self.band_edges, sample_rate);
+            }
+            let interval = self.settings.hop as f32 / sample_rate as f32;
+            self.envelope.set_interval(interval);
+            self.normalizer.set_interval(interval);
+            self.onset.set_interval(interval);
+            self.tempo.set_interval(interval);
+        }
+    }
+
+    /// 对最新窗口的交错采样逐声道执行频谱分析
+    ///
+    /// 立体声输入按左右声道分别做FFT，两组频段共享同一归一化参考值，
+    /// 单声道输入的左右两组频段相同。多分辨率模式下每一级只取窗口末尾
+    /// 最新的若干帧，各自计算所负责的频段。归一化后的结果经起音/释放平滑再输出
+    pub fn process(&mut self, samples: &[f32]) -> &SpectrumFrame {
+        let channels = self.channels;
+        for (channel, channel_bands) in self.bands.chunks_mut(self.band_count).enumerate() {
+            for stage in &mut self.stages {
+                let latest = &samples[(self.window_frames - stage.size) * channels..];
+                stage.process(
+                    latest,
+                    channel,
+                    channels,
+                    &mut channel_bands[stage.bands.clone()],
+                );
+            }
+            for (band, &weight) in channel_bands.iter_mut().zip(&self.band_weights) {
+                *band *= weight;
+            }
+        }
+        self.levels.copy_from_slice(&self.bands);
+        self.time += self.settings.hop as f64 / self.sample_rate as f64;
+        self.frame.onsets = *self.onset.process(&self.levels, self.time);
+        self.frame.tempo = *self
+            .tempo
+            .process(self.frame.onsets.flux[OnsetBand::Overall as usize]);
+        self.frame.time = self.time;
+        for (i, band) in self.bands.iter_mut().enumerate() {
+            *band *= self.band_gains[i % self.band_count];
+        }
+        self.normalizer.process(&mut self.bands);
+        self.envelope.process(&mut self.bands);
+        let (left, right) = self.bands.split_at(self.band_count);
+        self.frame.left.copy_from_slice(left);
+        self.frame
+            .right
+            .copy_from_slice(if right.is_empty() { left } else { right });
+        &self.frame
+    }
+}
+
+/// 单一FFT点数的分析级，负责一段连续的频段
+struct FftStage {
+    fft: Arc<dyn Fft<f32>>,         // 前向FFT计划
+    size: usize,                    // FFT点数
+    bands: Range<usize>,            // 负责的频段下标范围
+    window: Vec<f32>,               // 窗函数系数表（已含校正因子）
+    bank: BandBank,                 // 按当前采样率构建的频段汇总方式
+    scales: Vec<f32>,               // 各频段幅值换算到 `--fft-size` 点FFT量纲的系数
+    fft_buffer: Vec<Complex<f32>>,  // FFT输入/输出缓冲区
+    fft_scratch: Vec<Complex<f32>>, // FFT内部使用的暂存区
+}
+
+impl FftStage {
+    fn new(
+        settings: &AnalyzerSettings,
+        size: usize,
+        bands: Range<usize>,
+        edges: &[(f32, f32)],
+        sample_rate: u32,
+    ) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(size);
+        let scratch_len = fft.get_inplace_scratch_len();
+        // 常Q频域核自带窗函数，输入FFT的信号不再加窗
+        let window = match settings.mode {
+            AnalyzerMode::Cqt => vec![1.0; size],
+            _ => window::coefficients(settings.window, size, settings.window_correction),
+        };
+        let mut stage = Self {
+            fft,
+            size,
+            window,
+            bank: BandBank::new(settings, &edges[bands.clone()], sample_rate, size),
+            scales: Vec::new(),
+            bands,
+            fft_buffer: vec![Complex::new(0.0, 0.0); size],
+            fft_scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+        };
+        stage.scales = stage.band_scales(settings, edges, sample_rate);
+        stage
+    }
+
+    fn rebuild_bank(
+        &mut self,
+        settings: &AnalyzerSettings,
+        edges: &[(f32, f32)],
+        sample_rate: u32,
+    ) {
+        self.bank = BandBank::new(settings, &edges[self.bands.clone()], sample_rate, self.size);
+        self.scales = self.band_scales(settings, edges, sample_rate);
+    }
+
+    /// 各频段换算到 `--fft-size` 点FFT量纲的系数
+    ///
+    /// 正弦的FFT峰值与点数成正比，而频段取所覆盖bin的均方根，所以按点数之比、
+    /// 再乘以两种点数下频段等效bin数之比的平方根换算，单频信号的读数与单一FFT模式一致。
+    /// 宽频段的bin数与点数成正比，系数约为点数比的平方根，噪声读数同样一致；
+    /// 在 `--fft-size` 下不足一个bin宽的窄频段按一个bin计，噪声读数会偏低
+    fn band_scales(
+        &self,
+        settings: &AnalyzerSettings,
+        edges: &[(f32, f32)],
+        sample_rate: u32,
+    ) -> Vec<f32> {
+        let edges = &edges[self.bands.clone()];
+        let ratio = settings.fft_size as f32 / self.size as f32;
+        match &self.bank {
+            BandBank::Filterbank(bank) if self.size != settings.fft_size => {
+                let reference =
+                    Filterbank::new(settings.band_filter, edges, sample_rate, settings.fft_size);
+                bank.widths()
+                    .zip(reference.widths())
+                    .map(|(width, reference)| ratio * (width / reference).sqrt())
+                    .collect()
+            }
+            _ => vec![1.0; edges.len()],
+        }
+    }
 
-        if freq_ratio < 0.3 {
-            // 低频衰减：防止低频过强，衰减系数随频率增加而减小
-            let attenuation = 1.0 - (0.3 - freq_ratio) * 2.0;
-            *band *= attenuation; // 最小衰减到50%
-        } else if freq_ratio > 0.8 {
-            // 高频增强：提升高频可见度，增强系数随频率增加而增大
-            let boost = 1.0 + (freq_ratio - 0.8) * 1.5;
-            *band *= boost; // 最大增强到150%
+    /// 对 `samples` 开头 `size` 帧中的 `channel` 声道做FFT，结果写入 `out`
+    fn process(&mut self, samples: &[f32], channel: usize, channels: usize, out: &mut [f32]) {
+        // 反交错取出当前声道，乘以预计算的窗函数系数，抑制频谱泄漏
+        self.fft_buffer.fill(Complex::new(0.0, 0.0));
+        let channel_samples = samples.iter().skip(channel).step_by(channels);
+        for ((input, &sample), &w) in self
+            .fft_buffer
+            .iter_mut()
+            .zip(channel_samples)
+            .zip(&self.window)
+        {
+            *input = Complex::new(sample * w, 0.0);
+        }
+        self.fft
+            .process_with_scratch(&mut self.fft_buffer, &mut self.fft_scratch);
+        self.bank.process(&self.fft_buffer[..self.size / 2], out);
+        for (band, &scale) in out.iter_mut().zip(&self.scales) {
+            *band *= scale;
         }
-        // 中频段(30%-80%)保持原值不变
     }
 }
 
-fn improved_normalize_spectrum(bands: &mut [f32]) {
-    // 步骤1: 创建副本并排序以找到稳健的参考值
-    let mut sorted_bands = bands.to_vec();
-    sorted_bands.sort_by(|a, b| a.partial_cmp(b).unwrap());
+/// 把FFT结果汇总为各频段幅值的方式
+enum BandBank {
+    Filterbank(Filterbank), // FFT模式：频段滤波器组
+    ConstantQ(ConstantQ),   // 常Q模式：常Q频域核
+}
 
-    // 步骤2: 使用95百分位数作为参考值（排除极值影响）
-    let percentile_95_idx = (sorted_bands.len() as f32 * 0.95) as usize;
-    let reference_value = sorted_bands[percentile_95_idx.max(1)].max(1e-6); // 防止除零错误
+impl BandBank {
+    fn new(
+        settings: &AnalyzerSettings,
+        edges: &[(f32, f32)],
+        sample_rate: u32,
+        fft_size: usize,
+    ) -> Self {
+        match settings.mode {
+            AnalyzerMode::Cqt => BandBank::ConstantQ(ConstantQ::new(
+                edges,
+                settings.cqt_gamma,
+                sample_rate,
+                fft_size,
+            )),
+            _ => BandBank::Filterbank(Filterbank::new(
+                settings.band_filter,
+                edges,
+                sample_rate,
+                fft_size,
+            )),
+        }
+    }
 
-    // 步骤3: 对每个频段进行归一化和增强处理
-    for band in bands.iter_mut() {
-        let normalized = (*band / reference_value).clamp(0.0, 1.0); // 归一化到[0,1]范围
-        *band = s_curve_enhancement(normalized); // 应用S型曲线增强
+    fn process(&self, spectrum: &[Complex<f32>], out: &mut [f32]) {
+        match self {
+            BandBank::Filterbank(filterbank) => filterbank.process(spectrum, out),
+            BandBank::ConstantQ(cqt) => cqt.process(spectrum, out),
+        }
     }
 }
 
-fn s_curve_enhancement(x: f32) -> f32 {
-    if x < 0.1 {
-        // 低值区域：轻微压缩
-        x * 0.1
-    } else if x > 0.9 {
-        // 高值区域：显著增强
-        0.9 + (x - 0.9) * 5.0
-    } else {
-        // 中值区域：二次曲线增强
-        let t = (x - 0.1) / 0.8;
-        0.01 + 0.98 * t.powf(2.0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::normalize::NormalizeMode;
+    use std::f32::consts::TAU;
+
+    /// 不平滑、不计权、按固定dB范围归一化的设置，频段值只取决于当前窗口
+    fn settings() -> AnalyzerSettings {
+        AnalyzerSettings {
+            window: WindowKind::Hann,
+            window_correction: WindowCorrection::Energy,
+            fft_size: 4096,
+            bands: 64,
+            scale: BandScale::Log,
+            min_freq: 20.0,
+            max_freq: 20000.0,
+            band_filter: BandFilter::Rectangular,
+            mode: AnalyzerMode::Fft,
+            bins_per_octave: 12,
+            cqt_gamma: 0.0,
+            resolutions: vec![16384, 4096, 1024],
+            crossovers: vec![250.0, 2500.0],
+            hop: 512,
+            smoothing: Smoothing {
+                attack_ms: 0.0,
+                release_ms: 0.0,
+                overrides: Vec::new(),
+            },
+            normalize: NormalizeSettings {
+                mode: NormalizeMode::FixedDb,
+                agc_attack_ms: 300.0,
+                agc_decay_ms: 8000.0,
+                min_gain_db: -12.0,
+                max_gain_db: 40.0,
+                agc_per_band: false,
+                floor_db: -100.0,
+                ceiling_db: 0.0,
+            },
+            weighting: Weighting::Z,
+            onset: OnsetSettings {
+                sensitivity: 1.5,
+                min_interval_ms: 100.0,
+            },
+            tempo: TempoSettings {
+                min_bpm: 60.0,
+                max_bpm: 180.0,
+            },
+        }
+    }
+
+    /// 幅度0.1（-20 dBFS）的正弦
+    fn sine(freq: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| 0.1 * (TAU * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    /// 确定性的均匀白噪声，幅度范围 [-0.5, 0.5)
+    fn noise(len: usize) -> Vec<f32> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                // xorshift32
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect()
+    }
+
+    /// 数值最大的下标
+    fn peak(values: &[f32]) -> usize {
+        (0..values.len())
+            .max_by(|&a, &b| values[a].total_cmp(&values[b]))
+            .unwrap()
+    }
+
+    #[test]
+    fn set_sample_rate_rebuilds_band_table() {
+        let settings = settings();
+        let edges = settings.band_edges();
+        for (from, to) in [
+            (44100, 96000),
+            (48000, 192000),
+            (192000, 44100),
+            (96000, 48000),
+        ] {
+            let mut analyzer = SpectrumAnalyzer::new(&settings, from, 1);
+            analyzer.set_sample_rate(to);
+            let mut fresh = SpectrumAnalyzer::new(&settings, to, 1);
+            let samples = sine(1000.0, to, analyzer.window_len());
+            let rebuilt = analyzer.process(&samples).left.clone();
+            assert_eq!(
+                rebuilt,
+                fresh.process(&samples).left,
+                "{} -> {} Hz",
+                from,
+                to
+            );
+
+            // 1 kHz正弦的能量应落在包含1 kHz的频段
+            let (low, high) = edges[peak(&rebuilt)];
+            assert!(
+                (low..high).contains(&1000.0),
+                "{} -> {} Hz: 峰值频段 {}..{} Hz",
+                from,
+                to,
+                low,
+                high
+            );
+        }
+    }
+
+    #[test]
+    fn analyzes_stereo_channels_separately() {
+        let settings = settings();
+        let edges = settings.band_edges();
+        let mut analyzer = SpectrumAnalyzer::new(&settings, 48000, 2);
+        // 只有左声道有1 kHz正弦，右声道静音
+        let left = sine(1000.0, 48000, analyzer.window_len() / 2);
+        let samples: Vec<f32> = left.iter().flat_map(|&l| [l, 0.0]).collect();
+        let frame = analyzer.process(&samples);
+        let (low, high) = edges[peak(&frame.left)];
+        assert!(
+            (low..high).contains(&1000.0),
+            "左声道峰值频段 {}..{} Hz",
+            low,
+            high
+        );
+        assert!(
+            frame.right.iter().all(|&band| band == 0.0),
+            "右声道应在下限: {:?}",
+            frame.right
+        );
+
+        // 单声道输入的右声道与左声道相同
+        let mut mono = SpectrumAnalyzer::new(&settings, 48000, 1);
+        let frame = mono.process(&left);
+        assert!(frame.left.iter().any(|&band| band > 0.0));
+        assert_eq!(frame.left, frame.right);
+    }
+
+    #[test]
+    fn multires_stages_partition_bands_at_crossovers() {
+        let settings = AnalyzerSettings {
+            mode: AnalyzerMode::MultiRes,
+            ..settings()
+        };
+        let edges = settings.band_edges();
+        let layout = settings.stage_layout(&edges);
+        assert_eq!(
+            layout.iter().map(|(size, _)| *size).collect::<Vec<_>>(),
+            settings.resolutions
+        );
+        // 各级的频段首尾相接，覆盖全部频段且互不重叠
+        let mut next = 0;
+        for (level, (_, bands)) in layout.iter().enumerate() {
+            assert_eq!(bands.start, next);
+            next = bands.end;
+            // 每个频段的几何中心都落在本级的分频范围内
+            let lower = level.checked_sub(1).map_or(0.0, |i| settings.crossovers[i]);
+            let upper = settings
+                .crossovers
+                .get(level)
+                .copied()
+                .unwrap_or(f32::INFINITY);
+            for &(low, high) in &edges[bands.clone()] {
+                let center = (low * high).sqrt();
+                assert!(
+                    (lower..upper).contains(&center),
+                    "第{}级: {} Hz",
+                    level,
+                    center
+                );
+            }
+        }
+        assert_eq!(next, edges.len());
+    }
+
+    #[test]
+    fn multires_levels_match_fft_across_crossovers() {
+        const SAMPLE_RATE: u32 = 48000;
+        const FRAMES: usize = 200;
+        const STEP: usize = 1024;
+        let fft_settings = settings();
+        let multires_settings = AnalyzerSettings {
+            mode: AnalyzerMode::MultiRes,
+            ..settings()
+        };
+        let mut fft = SpectrumAnalyzer::new(&fft_settings, SAMPLE_RATE, 1);
+        let mut multires = SpectrumAnalyzer::new(&multires_settings, SAMPLE_RATE, 1);
+        let window = multires.window_len();
+        let samples = noise(window + FRAMES * STEP);
+
+        // 在线性功率上对多帧求平均，避免对dB取平均时的偏差随bin数变化
+        let edges = multires_settings.band_edges();
+        let mut fft_power = vec![0.0f64; edges.len()];
+        let mut multires_power = vec![0.0f64; edges.len()];
+        for frame in 0..FRAMES {
+            let latest = &samples[frame * STEP..][..window];
+            multires.process(latest);
+            fft.process(&latest[window - fft.window_len()..]);
+            for (power, &level) in multires_power.iter_mut().zip(multires.levels()) {
+                *power += (level * level) as f64;
+            }
+            for (power, &level) in fft_power.iter_mut().zip(fft.levels()) {
+                *power += (level * level) as f64;
+            }
+        }
+        let db = |power: f64| 10.0 * power.log10();
+
+        // 白噪声在各频段的均方根幅值相同：分频点两侧的频段应与单一FFT模式一致，且彼此相接
+        let layout = multires_settings.stage_layout(&edges);
+        for pair in layout.windows(2) {
+            let below = pair[0].1.end - 1;
+            let above = pair[1].1.start;
+            for band in [below, above] {
+                let diff = db(multires_power[band]) - db(fft_power[band]);
+                assert!(
+                    diff.abs() < 1.0,
+                    "频段 {:?} Hz: 多分辨率与单一FFT相差 {:.2} dB",
+                    edges[band],
+                    diff
+                );
+            }
+            let step = db(multires_power[above]) - db(multires_power[below]);
+            assert!(
+                step.abs() < 1.0,
+                "分频点 {} Hz 处跳变 {:.2} dB",
+                edges[above].0,
+                step
+            );
+        }
+    }
+
+    #[test]
+    fn multires_tone_levels_match_fft() {
+        const SAMPLE_RATE: u32 = 48000;
+        // 矩形窗下，恰好落在bin上的正弦没有频谱泄漏，读数只取决于各级的换算系数
+        let fft_settings = AnalyzerSettings {
+            window: WindowKind::Rectangular,
+            ..settings()
+        };
+        let multires_settings = AnalyzerSettings {
+            mode: AnalyzerMode::MultiRes,
+            ..fft_settings.clone()
+        };
+        let edges = multires_settings.band_edges();
+        let bin_width = |size: usize| SAMPLE_RATE as f32 / size as f32;
+        // 与矩形滤波器组相同的bin范围：[floor(low/bw), max(floor(high/bw), start+1))
+        let covers = |(low, high): (f32, f32), size: usize, freq: f32| {
+            let bin = (freq / bin_width(size)).round() as usize;
+            let start = (low / bin_width(size)) as usize;
+            let end = ((high / bin_width(size)) as usize).max(start + 1);
+            (start..end).contains(&bin)
+        };
+
+        // 每个频段取一个在本级和 `--fft-size` 下都恰好落在频段所覆盖bin上的正弦
+        let mut tones = Vec::new();
+        let mut narrow = false;
+        let layout = multires_settings.stage_layout(&edges);
+        for (size, bands) in layout.iter().cloned() {
+            let step = bin_width(size.min(fft_settings.fft_size));
+            for band in bands {
+                let (low, high) = edges[band];
+                let first = ((low / step).floor() as usize).max(2);
+                let tone = (first..)
+                    .map(|k| k as f32 * step)
+                    .take_while(|&freq| freq < high)
+                    .find(|&freq| {
+                        covers(edges[band], size, freq)
+                            && covers(edges[band], fft_settings.fft_size, freq)
+                    });
+                if let Some(freq) = tone {
+                    narrow |= high - low < bin_width(fft_settings.fft_size);
+                    tones.push((band, freq));
+                }
+            }
+        }
+        // 须包含长窗级中不足一个 `--fft-size` bin宽的窄频段，以及各分频点两侧的频段
+        assert!(narrow);
+        for pair in layout.windows(2) {
+            for band in [pair[0].1.end - 1, pair[1].1.start] {
+                assert!(tones.iter().any(|&(tone_band, _)| tone_band == band));
+            }
+        }
+
+        for (band, freq) in tones {
+            let mut fft = SpectrumAnalyzer::new(&fft_settings, SAMPLE_RATE, 1);
+            let mut multires = SpectrumAnalyzer::new(&multires_settings, SAMPLE_RATE, 1);
+            let samples = sine(freq, SAMPLE_RATE, multires.window_len());
+            multires.process(&samples);
+            fft.process(&samples[samples.len() - fft.window_len()..]);
+            let diff = 20.0 * (multires.levels()[band] / fft.levels()[band]).log10();
+            assert!(
+                diff.abs() < 1.0,
+                "{} Hz 正弦在频段 {:?} Hz: 多分辨率与单一FFT相差 {:.2} dB",
+                freq,
+                edges[band],
+                diff
+            );
+        }
     }
 }
@@ -0,0 +1,220 @@
+//! 频段滤波器组模块
+//!
+//! 把FFT功率谱汇总为各频段的均方根幅值。默认按频段边界截取整数bin范围求平均；
+//! 三角形或高斯滤波器按频段中心的小数bin位置对相邻bin加权，
+//! 使不足一个bin宽的低频频段也能得到平滑且互不相同的值
+
+use rustfft::num_complex::Complex;
+use std::str::FromStr;
+
+/// 频段滤波器形状
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BandFilter {
+    Rectangular, // 矩形：频段内整数bin等权求平均
+    Triangular,  // 三角形：中心权重为1，向两侧边界线性衰减
+    Gaussian,    // 高斯：以频段中心为均值、半带宽为标准差
+}
+
+impl FromStr for BandFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rectangular" => Ok(BandFilter::Rectangular),
+            "triangular" => Ok(BandFilter::Triangular),
+            "gaussian" => Ok(BandFilter::Gaussian),
+            _ => Err(format!(
+                "未知的频段滤波器 `{}`，可选: rectangular, triangular, gaussian",
+                s
+            )),
+        }
+    }
+}
+
+/// 预计算的频段滤波器组
+///
+/// 所有频段的权重依次存放在同一个数组中，`process` 不做堆分配
+pub struct Filterbank {
+    kind: BandFilter,            // 滤波器形状
+    ranges: Vec<(usize, usize)>, // 每个频段覆盖的FFT bin范围 [start, end)
+    offsets: Vec<usize>,         // 每个频段权重在 `weights` 中的起始位置
+    weights: Vec<f32>,           // 各bin的权重（矩形滤波器为空）
+    norms: Vec<f32>,             // 每个频段的归一化系数（权重和的倒数）
+}
+
+impl Filterbank {
+    /// 按频段边界频率、采样率和FFT点数构建滤波器组
+    pub fn new(kind: BandFilter, edges: &[(f32, f32)], sample_rate: u32, fft_size: usize) -> Self {
+        let freq_resolution = sample_rate as f32 / fft_size as f32;
+        let max_bin = fft_size / 2; // 只使用正频率部分，不含直流分量
+        let mut bank = Self {
+            kind,
+            ranges: Vec::with_capacity(edges.len()),
+            offsets: Vec::with_capacity(edges.len()),
+            weights: Vec::new(),
+            norms: Vec::with_capacity(edges.len()),
+        };
+        for &(freq_start, freq_end) in edges {
+            let offset = bank.weights.len();
+            let (range, norm) = match kind {
+                BandFilter::Rectangular => {
+                    let start_idx = ((freq_start / freq_resolution) as usize).clamp(1, max_bin - 1);
+                    let end_idx = ((freq_end / freq_resolution) as usize)
+                        .max(start_idx + 1)
+                        .min(max_bin);
+                    ((start_idx, end_idx), 1.0 / (end_idx - start_idx) as f32)
+                }
+                BandFilter::Triangular | BandFilter::Gaussian => {
+                    // 频段中心取几何中心，以小数bin表示；半宽至少一个bin，
+                    // 窄频段因此在相邻两个bin之间线性插值
+                    let low = freq_start / freq_resolution;
+                    let high = freq_end / freq_resolution;
+                    let center = (low * high).sqrt().clamp(1.0, (max_bin - 1) as f32);
+                    let lower_width = (center - low).max(1.0);
+                    let upper_width = (high - center).max(1.0);
+                    // 高斯滤波器截取到3倍标准差
+                    let reach = if kind == BandFilter::Gaussian {
+                        3.0
+                    } else {
+                        1.0
+                    };
+                    let start_idx = ((center - lower_width * reach).ceil() as usize).max(1);
+                    let end_idx = ((center + upper_width * reach).floor() as usize + 1)
+                        .clamp(start_idx + 1, max_bin);
+                    for bin in start_idx..end_idx {
+                        let distance = bin as f32 - center;
+                        let width = if distance < 0.0 {
+                            lower_width
+                        } else {
+                            upper_width
+                        };
+                        let x = distance / width;
+                        let weight = match kind {
+                            BandFilter::Gaussian => (-0.5 * x * x).exp(),
+                            _ => (1.0 - x.abs()).max(0.0),
+                        };
+                        bank.weights.push(weight);
+                    }
+                    let sum: f32 = bank.weights[offset..].iter().sum();
+                    ((start_idx, end_idx), 1.0 / sum.max(1e-6))
+                }
+            };
+            bank.offsets.push(offset);
+            bank.ranges.push(range);
+            bank.norms.push(norm);
+        }
+        bank
+    }
+
+    /// 每个频段覆盖的等效bin数（权重和）
+    pub fn widths(&self) -> impl Iterator<Item = f32> + '_ {
+        self.norms.iter().map(|norm| 1.0 / norm)
+    }
+
+    /// 计算各频段的均方根幅值，写入 `out`
+    pub fn process(&self, spectrum: &[Complex<f32>], out: &mut [f32]) {
+        for (i, band) in out.iter_mut().enumerate() {
+            let (start_idx, end_idx) = self.ranges[i];
+            let sum_squares = match self.kind {
+                BandFilter::Rectangular => compute_magnitudes(spectrum, start_idx, end_idx),
+                BandFilter::Triangular | BandFilter::Gaussian => {
+                    let weights = &self.weights[self.offsets[i]..][..end_idx - start_idx];
+                    spectrum[start_idx..end_idx]
+                        .iter()
+                        .zip(weights)
+                        .map(|(bin, &w)| w * (bin.re * bin.re + bin.im * bin.im))
+                        .sum()
+                }
+            };
+            *band = (sum_squares * self.norms[i]).sqrt();
+        }
+    }
+}
+
+#[inline(always)]
+fn compute_magnitudes(spectrum: &[Complex<f32>], start_idx: usize, end_idx: usize) -> f32 {
+    let mut sum_squares = 0.0f32;
+    let iter_end = start_idx + ((end_idx - start_idx) / 4) * 4;
+    for i in (start_idx..iter_end).step_by(4) {
+        let mag_sq1 = spectrum[i].re * spectrum[i].re + spectrum[i].im * spectrum[i].im;
+        let mag_sq2 =
+            spectrum[i + 1].re * spectrum[i + 1].re + spectrum[i + 1].im * spectrum[i + 1].im;
+        let mag_sq3 =
+            spectrum[i + 2].re * spectrum[i + 2].re + spectrum[i + 2].im * spectrum[i + 2].im;
+        let mag_sq4 =
+            spectrum[i + 3].re * spectrum[i + 3].re + spectrum[i + 3].im * spectrum[i + 3].im;
+        sum_squares += mag_sq1 + mag_sq2 + mag_sq3 + mag_sq4;
+    }
+    for bin in &spectrum[iter_end..end_idx] {
+        let mag_sq = bin.re * bin.re + bin.im * bin.im;
+        sum_squares += mag_sq;
+    }
+    sum_squares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::scale::{BandScale, band_edges};
+
+    const RATES: [u32; 4] = [44100, 48000, 96000, 192000];
+    const FFT_SIZE: usize = 8192;
+
+    #[test]
+    fn rectangular_bins_match_edges_at_each_rate() {
+        let edges = band_edges(BandScale::Log, 50.0, 20000.0, 64);
+        for rate in RATES {
+            let bin_width = rate as f32 / FFT_SIZE as f32;
+            let bank = Filterbank::new(BandFilter::Rectangular, &edges, rate, FFT_SIZE);
+            for (&(start, end), &(low, high)) in bank.ranges.iter().zip(&edges) {
+                let start_hz = start as f32 * bin_width;
+                let end_hz = end as f32 * bin_width;
+                assert!(
+                    (start_hz - low).abs() <= bin_width,
+                    "{} Hz: 起始bin {} ({} Hz) 偏离 {} Hz",
+                    rate,
+                    start,
+                    start_hz,
+                    low
+                );
+                assert!(
+                    (end_hz - high).abs() <= bin_width,
+                    "{} Hz: 结束bin {} ({} Hz) 偏离 {} Hz",
+                    rate,
+                    end,
+                    end_hz,
+                    high
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_filters_center_on_band_center() {
+        let edges = band_edges(BandScale::Log, 50.0, 20000.0, 64);
+        for rate in RATES {
+            let bin_width = rate as f32 / FFT_SIZE as f32;
+            for kind in [BandFilter::Triangular, BandFilter::Gaussian] {
+                let bank = Filterbank::new(kind, &edges, rate, FFT_SIZE);
+                for (i, &(low, high)) in edges.iter().enumerate() {
+                    let (start, end) = bank.ranges[i];
+                    let weights = &bank.weights[bank.offsets[i]..][..end - start];
+                    // 权重最大的bin应落在频段中心一个bin宽度以内
+                    let peak = (0..weights.len())
+                        .max_by(|&a, &b| weights[a].total_cmp(&weights[b]))
+                        .unwrap();
+                    let peak_hz = (start + peak) as f32 * bin_width;
+                    let center = (low * high).sqrt();
+                    assert!(
+                        (peak_hz - center).abs() <= bin_width,
+                        "{} Hz {:?}: 峰值bin {} Hz 偏离中心 {} Hz",
+                        rate,
+                        kind,
+                        peak_hz,
+                        center
+                    );
+                }
+            }
+        }
+    }
+}
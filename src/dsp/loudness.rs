@@ -0,0 +1,413 @@
+//! 响度测量模块（ITU-R BS.1770 / EBU R128）
+//!
+//! 对采集到的原始多声道采样做K计权，按声道权重（LFE不计、环绕声道1.41）求和，
+//! 以100 ms为步长得到瞬时（400 ms）和短期（3 s）响度。
+//! 综合响度对400 ms块做 -70 LUFS绝对门限和 -10 LU相对门限，响度范围（LRA）对短期响度做
+//! -70 LUFS绝对门限和 -20 LU相对门限后取10%～95%分位差。两者都用0.1 LU宽的直方图累积，
+//! 内存占用不随时长增长。真峰值经4倍过采样插值后取最大绝对值
+
+use crate::audio::StreamFormat;
+use crate::audio::convert::{
+    SPEAKER_BACK_LEFT, SPEAKER_BACK_RIGHT, SPEAKER_LOW_FREQUENCY, SPEAKER_SIDE_LEFT,
+    SPEAKER_SIDE_RIGHT, channel_index,
+};
+use std::f64::consts::PI;
+
+const STEP_SECS: f64 = 0.1; // 块步长（秒）
+const MOMENTARY_STEPS: usize = 4; // 瞬时响度窗口：400 ms
+const SHORT_TERM_STEPS: usize = 30; // 短期响度窗口：3 s
+const ABSOLUTE_GATE: f64 = -70.0; // 绝对门限（LUFS）
+const INTEGRATED_GATE: f64 = -10.0; // 综合响度的相对门限（LU）
+const RANGE_GATE: f64 = -20.0; // 响度范围的相对门限（LU）
+const HISTOGRAM_MAX: f64 = 10.0; // 直方图上限（LUFS），更响的块计入最高一格
+const HISTOGRAM_STEP: f64 = 0.1; // 直方图每格宽度（LU）
+const SURROUND_WEIGHT: f64 = 1.41; // 环绕声道的权重
+const OVERSAMPLE: usize = 4; // 真峰值过采样倍数
+const TAPS_PER_PHASE: usize = 12; // 插值滤波器每相的抽头数
+
+/// 响度测量结果，随频谱帧一起发布
+#[derive(Clone, Copy, Debug)]
+pub struct LoudnessState {
+    pub momentary: f32,  // 瞬时响度（LUFS）
+    pub short_term: f32, // 短期响度（LUFS）
+    pub integrated: f32, // 综合响度（LUFS）
+    pub range: f32,      // 响度范围（LU）
+    pub true_peak: f32,  // 开始测量以来的最大真峰值（dBTP）
+}
+
+impl Default for LoudnessState {
+    fn default() -> Self {
+        Self {
+            momentary: f32::NEG_INFINITY,
+            short_term: f32::NEG_INFINITY,
+            integrated: f32::NEG_INFINITY,
+            range: 0.0,
+            true_peak: f32::NEG_INFINITY,
+        }
+    }
+}
+
+/// 二阶IIR滤波器（直接II型转置）
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],     // 分子系数
+    a: [f64; 2],     // 分母系数 a1、a2（a0已归一化为1）
+    state: [f64; 2], // 延迟单元
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// 按采样率计算K计权的两级滤波器：高频搁架 + 高通（BS.1770的连续时间原型）
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    // 第一级：约+4 dB的高频搁架，模拟头部声学效应
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+    // 第二级：约38 Hz的高通（RLB计权）
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+/// 4倍过采样插值滤波器：Hann窗截断的sinc，按相位拆分
+fn interpolation_phases() -> Vec<[f64; TAPS_PER_PHASE]> {
+    let taps = OVERSAMPLE * TAPS_PER_PHASE;
+    let center = (taps - 1) as f64 / 2.0;
+    (0..OVERSAMPLE)
+        .map(|phase| {
+            let mut coefficients = [0.0; TAPS_PER_PHASE];
+            for (k, c) in coefficients.iter_mut().enumerate() {
+                let n = (k * OVERSAMPLE + phase) as f64;
+                let x = (n - center) / OVERSAMPLE as f64;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * x).sin() / (PI * x)
+                };
+                let window = 0.5 - 0.5 * (2.0 * PI * (n + 0.5) / taps as f64).cos();
+                *c = sinc * window;
+            }
+            coefficients
+        })
+        .collect()
+}
+
+/// 0.1 LU宽的响度直方图，每格记录块数和能量之和
+struct Histogram {
+    counts: Vec<u64>,   // 各格的块数
+    energies: Vec<f64>, // 各格块能量（均方值）之和
+}
+
+impl Histogram {
+    fn new() -> Self {
+        let bins = ((HISTOGRAM_MAX - ABSOLUTE_GATE) / HISTOGRAM_STEP).ceil() as usize;
+        Self {
+            counts: vec![0; bins],
+            energies: vec![0.0; bins],
+        }
+    }
+
+    /// 记录一个块，低于绝对门限的块不计
+    fn add(&mut self, energy: f64) {
+        let lufs = energy_to_lufs(energy);
+        if lufs < ABSOLUTE_GATE {
+            return;
+        }
+        let bin = (((lufs - ABSOLUTE_GATE) / HISTOGRAM_STEP) as usize).min(self.counts.len() - 1);
+        self.counts[bin] += 1;
+        self.energies[bin] += energy;
+    }
+
+    /// 某响度所在的格
+    fn bin(&self, lufs: f64) -> usize {
+        (((lufs - ABSOLUTE_GATE) / HISTOGRAM_STEP).ceil().max(0.0) as usize).min(self.counts.len())
+    }
+
+    /// 从 `start` 格起所有块的平均能量，没有块时返回None
+    fn mean_energy(&self, start: usize) -> Option<f64> {
+        let count: u64 = self.counts[start..].iter().sum();
+        (count > 0).then(|| self.energies[start..].iter().sum::<f64>() / count as f64)
+    }
+
+    /// 绝对门限和相对门限（LU）都通过的起始格
+    fn gated_start(&self, relative_gate: f64) -> Option<usize> {
+        let mean = self.mean_energy(0)?;
+        Some(self.bin(energy_to_lufs(mean) + relative_gate))
+    }
+}
+
+/// 均方值换算为响度（LUFS）
+fn energy_to_lufs(energy: f64) -> f64 {
+    if energy > 0.0 {
+        -0.691 + 10.0 * energy.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+/// EBU R128响度计
+pub struct LoudnessMeter {
+    format: StreamFormat,                // 当前流格式，变化时重新初始化
+    weights: Vec<f64>,                   // 各声道的权重
+    filters: Vec<[Biquad; 2]>,           // 各声道的K计权滤波器
+    step_len: usize,                     // 每个100 ms块的帧数
+    step_frames: usize,                  // 当前块已累积的帧数
+    step_sum: f64,                       // 当前块的加权平方和
+    steps: [f64; SHORT_TERM_STEPS],      // 最近30个块的均方值，环形存储
+    steps_pos: usize,                    // 环形存储的下一个写入位置
+    steps_len: usize,                    // 已完成的块数（最多30）
+    integrated: Histogram,               // 400 ms块的直方图
+    range: Histogram,                    // 短期响度的直方图
+    phases: Vec<[f64; TAPS_PER_PHASE]>,  // 过采样插值滤波器
+    history: Vec<[f64; TAPS_PER_PHASE]>, // 各声道插值用的最近采样，最新的在前
+    peak: f64,                           // 最大真峰值（线性）
+    state: LoudnessState,                // 对外发布的测量结果
+}
+
+impl LoudnessMeter {
+    /// 按流格式创建响度计
+    pub fn new(format: &StreamFormat) -> Self {
+        let channels = format.channels as usize;
+        // 按声道布局确定权重：LFE不计入，环绕声道1.41，其余为1
+        let mut weights = vec![1.0; channels];
+        let mut weight = |speaker: u32, value: f64| {
+            if let Some(index) = channel_index(format.channel_mask, speaker)
+                && index < channels
+            {
+                weights[index] = value;
+            }
+        };
+        weight(SPEAKER_LOW_FREQUENCY, 0.0);
+        for speaker in [
+            SPEAKER_BACK_LEFT,
+            SPEAKER_BACK_RIGHT,
+            SPEAKER_SIDE_LEFT,
+            SPEAKER_SIDE_RIGHT,
+        ] {
+            weight(speaker, SURROUND_WEIGHT);
+        }
+        let sample_rate = format.sample_rate as f64;
+        Self {
+            format: *format,
+            weights,
+            filters: vec![k_weighting(sample_rate); channels],
+            step_len: ((sample_rate * STEP_SECS).round() as usize).max(1),
+            step_frames: 0,
+            step_sum: 0.0,
+            steps: [0.0; SHORT_TERM_STEPS],
+            steps_pos: 0,
+            steps_len: 0,
+            integrated: Histogram::new(),
+            range: Histogram::new(),
+            phases: interpolation_phases(),
+            history: vec![[0.0; TAPS_PER_PHASE]; channels],
+            peak: 0.0,
+            state: LoudnessState::default(),
+        }
+    }
+
+    /// 最新的测量结果
+    pub fn state(&self) -> &LoudnessState {
+        &self.state
+    }
+
+    /// 处理一批交错采样；流格式改变时从头开始测量
+    pub fn process(&mut self, format: &StreamFormat, samples: &[f32]) {
+        if *format != self.format {
+            *self = Self::new(format);
+        }
+        let channels = self.weights.len();
+        if channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let x = sample as f64;
+                let [shelf, high_pass] = &mut self.filters[channel];
+                let y = high_pass.process(shelf.process(x));
+                self.step_sum += self.weights[channel] * y * y;
+
+                // 插值得到相邻采样之间的值，取最大绝对值
+                let history = &mut self.history[channel];
+                history.copy_within(..TAPS_PER_PHASE - 1, 1);
+                history[0] = x;
+                self.peak = self.peak.max(x.abs());
+                for phase in &self.phases {
+                    let value: f64 = phase.iter().zip(history.iter()).map(|(c, x)| c * x).sum();
+                    self.peak = self.peak.max(value.abs());
+                }
+            }
+            self.step_frames += 1;
+            if self.step_frames == self.step_len {
+                self.finish_step();
+            }
+        }
+    }
+
+    /// 完成一个100 ms块，更新各项响度
+    fn finish_step(&mut self) {
+        self.steps[self.steps_pos] = self.step_sum / self.step_len as f64;
+        self.steps_pos = (self.steps_pos + 1) % SHORT_TERM_STEPS;
+        self.steps_len = (self.steps_len + 1).min(SHORT_TERM_STEPS);
+        self.step_sum = 0.0;
+        self.step_frames = 0;
+
+        // 最近 `count` 个块的平均能量
+        let recent = |count: usize| {
+            (0..count)
+                .map(|ago| {
+                    self.steps[(self.steps_pos + SHORT_TERM_STEPS - 1 - ago) % SHORT_TERM_STEPS]
+                })
+                .sum::<f64>()
+                / count as f64
+        };
+        if self.steps_len >= MOMENTARY_STEPS {
+            let momentary = recent(MOMENTARY_STEPS);
+            self.state.momentary = energy_to_lufs(momentary) as f32;
+            self.integrated.add(momentary);
+            if let Some(start) = self.integrated.gated_start(INTEGRATED_GATE)
+                && let Some(mean) = self.integrated.mean_energy(start)
+            {
+                self.state.integrated = energy_to_lufs(mean) as f32;
+            }
+        }
+        if self.steps_len >= SHORT_TERM_STEPS {
+            let short_term = recent(SHORT_TERM_STEPS);
+            self.state.short_term = energy_to_lufs(short_term) as f32;
+            self.range.add(short_term);
+            self.state.range = self.loudness_range() as f32;
+        }
+        self.state.true_peak = (20.0 * self.peak.log10()) as f32;
+    }
+
+    /// 门限后短期响度分布的95%与10%分位之差（LU）
+    fn loudness_range(&self) -> f64 {
+        let Some(start) = self.range.gated_start(RANGE_GATE) else {
+            return 0.0;
+        };
+        let counts = &self.range.counts[start..];
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        // 累计块数首次达到给定比例的格
+        let percentile = |fraction: f64| {
+            let target = (fraction * total as f64).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            counts
+                .iter()
+                .position(|&count| {
+                    seen += count;
+                    seen >= target
+                })
+                .unwrap_or(0)
+        };
+        (percentile(0.95) - percentile(0.10)) as f64 * HISTOGRAM_STEP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::SampleType;
+
+    const RATE: u32 = 48000;
+
+    fn stereo() -> StreamFormat {
+        StreamFormat::new(2, RATE, SampleType::F32)
+    }
+
+    /// 向响度计送入 `secs` 秒两声道相同的1 kHz正弦，`dbfs` 为峰值电平
+    fn feed_sine(meter: &mut LoudnessMeter, dbfs: f64, secs: f64) {
+        let amplitude = 10f64.powf(dbfs / 20.0);
+        let frames = (secs * RATE as f64) as usize;
+        let samples: Vec<f32> = (0..frames)
+            .flat_map(|i| {
+                let x = amplitude * (2.0 * PI * 1000.0 * i as f64 / RATE as f64).sin();
+                [x as f32; 2]
+            })
+            .collect();
+        meter.process(&stereo(), &samples);
+    }
+
+    #[test]
+    fn sine_at_minus_20_dbfs_reads_minus_20_lufs() {
+        let mut meter = LoudnessMeter::new(&stereo());
+        feed_sine(&mut meter, -20.0, 5.0);
+        let state = meter.state();
+        for (name, value) in [
+            ("momentary", state.momentary),
+            ("short-term", state.short_term),
+            ("integrated", state.integrated),
+        ] {
+            assert!((value + 20.0).abs() < 0.1, "{}: {} LUFS", name, value);
+        }
+    }
+
+    #[test]
+    fn blocks_below_absolute_gate_are_ignored() {
+        let mut meter = LoudnessMeter::new(&stereo());
+        feed_sine(&mut meter, -80.0, 3.0);
+        assert!((meter.state().momentary + 80.0).abs() < 0.1);
+        assert_eq!(meter.state().integrated, f32::NEG_INFINITY);
+        // 先前的安静块不拉低综合响度
+        feed_sine(&mut meter, -20.0, 10.0);
+        assert!((meter.state().integrated + 20.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn two_level_signal_gives_expected_range() {
+        // EBU Tech 3342 测试1：-20 dBFS和-30 dBFS各20秒，LRA为10 ± 1 LU
+        let mut meter = LoudnessMeter::new(&stereo());
+        feed_sine(&mut meter, -20.0, 20.0);
+        feed_sine(&mut meter, -30.0, 20.0);
+        let range = meter.state().range;
+        assert!((range - 10.0).abs() <= 1.0, "LRA {} LU", range);
+    }
+
+    #[test]
+    fn true_peak_exceeds_sample_peak_between_samples() {
+        // fs/4正弦相位45°时，采样值都落在峰值的0.707处
+        let samples: Vec<f32> = (0..RATE as usize)
+            .flat_map(|i| [(PI / 2.0 * i as f64 + PI / 4.0).sin() as f32; 2])
+            .collect();
+        let sample_peak = 20.0
+            * samples
+                .iter()
+                .fold(0f32, |peak, x| peak.max(x.abs()))
+                .log10();
+        let mut meter = LoudnessMeter::new(&stereo());
+        meter.process(&stereo(), &samples);
+        let true_peak = meter.state().true_peak;
+        assert!((sample_peak + 3.01).abs() < 0.01);
+        assert!(true_peak > sample_peak + 2.5, "真峰值 {} dBTP", true_peak);
+        assert!(true_peak.abs() < 0.5, "真峰值 {} dBTP", true_peak);
+    }
+}
@@ -1,2 +1,14 @@
+pub mod calibrate;
+pub mod cqt;
+pub mod envelope;
 pub mod fft;
+pub mod filterbank;
+pub mod loudness;
+pub mod normalize;
+pub mod onset;
+pub mod ring;
+pub mod scale;
 pub mod spectrum;
+pub mod tempo;
+pub mod weighting;
+pub mod window;
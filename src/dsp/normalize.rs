@@ -0,0 +1,221 @@
+//! 频段归一化模块
+//!
+//! 把频段幅值映射到显示用的 [0, 1] 范围，提供三种方式：
+//! - 百分位：每帧以自身的95百分位数为参考值，并做S型曲线增强
+//! - 自动增益：参考值按起音/衰减时间常数缓慢跟随电平，增益限制在给定范围内，
+//!   安静段落保持较矮，能体现音乐的动态
+//! - 固定dB：按满幅（dBFS）换算，把 [下限, 上限] dB 线性映射到 [0, 1]
+
+use crate::dsp::envelope::one_pole_coefficient;
+use std::str::FromStr;
+
+/// 归一化方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalizeMode {
+    Percentile, // 每帧按95百分位数归一化
+    Agc,        // 长时间自动增益控制
+    FixedDb,    // 固定dB范围
+}
+
+impl FromStr for NormalizeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "percentile" => Ok(NormalizeMode::Percentile),
+            "agc" => Ok(NormalizeMode::Agc),
+            "fixed-db" => Ok(NormalizeMode::FixedDb),
+            _ => Err(format!(
+                "未知的归一化方式 `{}`，可选: percentile, agc, fixed-db",
+                s
+            )),
+        }
+    }
+}
+
+/// 归一化设置
+#[derive(Clone, Debug)]
+pub struct NormalizeSettings {
+    pub mode: NormalizeMode, // 归一化方式
+    pub agc_attack_ms: f32,  // 电平升高时参考值的跟随时间常数（毫秒）
+    pub agc_decay_ms: f32,   // 电平降低时参考值的回落时间常数（毫秒）
+    pub min_gain_db: f32,    // 自动增益下限（dB）
+    pub max_gain_db: f32,    // 自动增益上限（dB）
+    pub agc_per_band: bool,  // 每个频段独立跟随参考值
+    pub floor_db: f32,       // 固定dB模式的下限（dBFS），对应柱高0
+    pub ceiling_db: f32,     // 固定dB模式的上限（dBFS），对应柱高1
+}
+
+/// 频段归一化器
+pub struct Normalizer {
+    settings: NormalizeSettings, // 归一化设置
+    full_scale: f32,             // 满幅正弦信号对应的频段幅值
+    attack: f32,                 // 参考值的起音系数
+    decay: f32,                  // 参考值的衰减系数
+    reference: Vec<f32>,         // 自动增益的参考电平（相对满幅），整体模式只用第一个
+    sorted: Vec<f32>,            // 求百分位数用的排序缓冲区
+}
+
+impl Normalizer {
+    /// 创建归一化器
+    ///
+    /// `values` 为每次处理的数值个数（频段数 × 声道数），
+    /// `full_scale` 为满幅正弦信号对应的频段幅值，`interval` 为相邻两次分析的间隔（秒）
+    pub fn new(
+        settings: &NormalizeSettings,
+        values: usize,
+        full_scale: f32,
+        interval: f32,
+    ) -> Self {
+        let mut normalizer = Self {
+            settings: settings.clone(),
+            full_scale,
+            attack: 1.0,
+            decay: 1.0,
+            reference: vec![0.0; values],
+            sorted: vec![0.0; values],
+        };
+        normalizer.set_interval(interval);
+        normalizer
+    }
+
+    /// 按新的分析间隔（秒）重算参考值的平滑系数
+    pub fn set_interval(&mut self, interval: f32) {
+        self.attack = one_pole_coefficient(interval, self.settings.agc_attack_ms);
+        self.decay = one_pole_coefficient(interval, self.settings.agc_decay_ms);
+    }
+
+    /// 就地把 `bands` 归一化到 [0, 1]
+    pub fn process(&mut self, bands: &mut [f32]) {
+        match self.settings.mode {
+            NormalizeMode::Percentile => {
+                let reference_value = percentile_95(bands, &mut self.sorted).max(1e-6); // 防止除零错误
+                for band in bands.iter_mut() {
+                    let normalized = (*band / reference_value).clamp(0.0, 1.0); // 归一化到[0,1]范围
+                    *band = s_curve_enhancement(normalized); // 应用S型曲线增强
+                }
+            }
+            NormalizeMode::Agc => {
+                let min_gain = db_to_linear(self.settings.min_gain_db);
+                let max_gain = db_to_linear(self.settings.max_gain_db);
+                let (attack, decay) = (self.attack, self.decay);
+                let follow = |reference: &mut f32, level: f32| {
+                    let alpha = if level > *reference { attack } else { decay };
+                    *reference += (level - *reference) * alpha;
+                    (1.0 / reference.max(1e-9)).clamp(min_gain, max_gain)
+                };
+                if self.settings.agc_per_band {
+                    for (band, reference) in bands.iter_mut().zip(&mut self.reference) {
+                        let level = *band / self.full_scale;
+                        *band = (level * follow(reference, level)).clamp(0.0, 1.0);
+                    }
+                } else {
+                    // 整体模式以95百分位数作为电平，与百分位模式的参考点一致
+                    let level = percentile_95(bands, &mut self.sorted) / self.full_scale;
+                    let gain = follow(&mut self.reference[0], level) / self.full_scale;
+                    for band in bands.iter_mut() {
+                        *band = (*band * gain).clamp(0.0, 1.0);
+                    }
+                }
+            }
+            NormalizeMode::FixedDb => {
+                let floor = self.settings.floor_db;
+                let range = (self.settings.ceiling_db - floor).max(1e-3);
+                for band in bands.iter_mut() {
+                    let db = 20.0 * (*band / self.full_scale).max(1e-9).log10();
+                    *band = ((db - floor) / range).clamp(0.0, 1.0);
+                }
+            }
+        }
+    }
+}
+
+/// 95百分位数（排除极值影响的稳健参考值）
+fn percentile_95(values: &[f32], sorted: &mut [f32]) -> f32 {
+    sorted.copy_from_slice(values);
+    sorted.sort_unstable_by(f32::total_cmp);
+    let percentile_95_idx = (sorted.len() as f32 * 0.95) as usize;
+    sorted[percentile_95_idx.max(1).min(sorted.len() - 1)]
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10_f32.powf(db / 20.0)
+}
+
+fn s_curve_enhancement(x: f32) -> f32 {
+    if x < 0.1 {
+        // 低值区域：轻微压缩
+        x * 0.1
+    } else if x > 0.9 {
+        // 高值区域：显著增强
+        0.9 + (x - 0.9) * 5.0
+    } else {
+        // 中值区域：二次曲线增强
+        let t = (x - 0.1) / 0.8;
+        0.01 + 0.98 * t.powf(2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL_SCALE: f32 = 2048.0;
+
+    fn settings(mode: NormalizeMode) -> NormalizeSettings {
+        NormalizeSettings {
+            mode,
+            agc_attack_ms: 300.0,
+            agc_decay_ms: 8000.0,
+            min_gain_db: -12.0,
+            max_gain_db: 40.0,
+            agc_per_band: false,
+            floor_db: -70.0,
+            ceiling_db: -10.0,
+        }
+    }
+
+    /// 相对满幅 `db` 分贝的频段幅值
+    fn level(db: f32) -> f32 {
+        FULL_SCALE * db_to_linear(db)
+    }
+
+    #[test]
+    fn fixed_db_maps_floor_and_ceiling() {
+        let mut normalizer =
+            Normalizer::new(&settings(NormalizeMode::FixedDb), 4, FULL_SCALE, 0.01);
+        let mut bands = [level(-70.0), level(-40.0), level(-10.0), level(0.0)];
+        normalizer.process(&mut bands);
+        for (band, expected) in bands.iter().zip([0.0, 0.5, 1.0, 1.0]) {
+            assert!((band - expected).abs() < 1e-4, "{:?}", bands);
+        }
+    }
+
+    #[test]
+    fn agc_reference_converges_to_steady_level() {
+        for per_band in [false, true] {
+            let settings = NormalizeSettings {
+                agc_per_band: per_band,
+                ..settings(NormalizeMode::Agc)
+            };
+            let mut normalizer = Normalizer::new(&settings, 4, FULL_SCALE, 0.01);
+            // 稳定的-20 dBFS输入持续3秒（10个起音时间常数）
+            let mut bands = [0.0; 4];
+            for _ in 0..300 {
+                bands = [level(-20.0); 4];
+                normalizer.process(&mut bands);
+            }
+            let reference = normalizer.reference[0];
+            assert!(
+                (reference / db_to_linear(-20.0) - 1.0).abs() < 1e-3,
+                "参考值 {}",
+                reference
+            );
+            assert!(
+                bands.iter().all(|&band| (band - 1.0).abs() < 1e-3),
+                "{:?}",
+                bands
+            );
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! 起音（onset）检测模块
+//!
+//! 对计权后的频段幅值做对数压缩，计算相邻两次分析之间的正向差分之和（谱通量），
+//! 分别统计全频带与低、中、高三个子频带。每个频带的阈值取最近一段时间谱通量的
+//! 均值加若干倍标准差，谱通量自下而上越过阈值、且距上次起音超过最小间隔时记为一次起音
+
+const LOW_MID_CROSSOVER: f32 = 200.0; // 低频与中频子频带的分界（Hz）
+const MID_HIGH_CROSSOVER: f32 = 2000.0; // 中频与高频子频带的分界（Hz）
+const COMPRESSION: f32 = 1000.0; // 对数压缩系数：log(1 + C × 相对满幅的幅值)
+const HISTORY_SECS: f32 = 1.0; // 自适应阈值统计的时间长度（秒）
+
+/// 起音检测的频带
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnsetBand {
+    Overall, // 全频带
+    Low,     // 低频（底鼓、贝斯）
+    Mid,     // 中频（军鼓、人声）
+    High,    // 高频（镲片、高帽）
+}
+
+/// 一次起音事件
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OnsetEvent {
+    pub time: f64,     // 起音时刻，音频流开始以来的秒数
+    pub strength: f32, // 谱通量超出阈值的倍数
+}
+
+/// 各频带的起音状态，随频谱帧一起发布
+///
+/// 渲染端比较 `last` 中的时刻与上次看到的时刻即可发现新的起音，
+/// 两次读取之间只会漏掉比最小间隔更密集的起音
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OnsetState {
+    pub flux: [f32; 4],                // 各频带当前的谱通量，按 `OnsetBand` 的顺序
+    pub last: [Option<OnsetEvent>; 4], // 各频带最近一次起音
+}
+
+impl OnsetState {
+    /// 指定频带的最近一次起音
+    pub fn last(&self, band: OnsetBand) -> Option<OnsetEvent> {
+        self.last[band as usize]
+    }
+}
+
+/// 起音检测设置
+#[derive(Clone, Debug)]
+pub struct OnsetSettings {
+    pub sensitivity: f32,     // 阈值 = 均值 + sensitivity × 标准差，越小越灵敏
+    pub min_interval_ms: f32, // 同一频带两次起音之间的最小间隔（毫秒）
+}
+
+/// 谱通量起音检测器
+pub struct OnsetDetector {
+    settings: OnsetSettings, // 检测设置
+    full_scale: f32,         // 满幅正弦信号对应的频段幅值
+    groups: Vec<usize>,      // 每个频段所属的子频带（1低、2中、3高）
+    group_sizes: [usize; 4], // 各频带包含的频段数
+    previous: Vec<f32>,      // 上一次分析的对数压缩幅值
+    current: Vec<f32>,       // 本次分析的对数压缩幅值
+    history: Vec<[f32; 4]>,  // 最近一段时间各频带谱通量的环形历史
+    history_pos: usize,      // 环形历史的下一个写入位置
+    history_len: usize,      // 环形历史中的有效数据个数
+    above: [bool; 4],        // 各频带上一次分析时谱通量是否高于阈值
+    state: OnsetState,       // 对外发布的起音状态
+}
+
+impl OnsetDetector {
+    /// 按频段边界频率创建检测器，`interval` 为相邻两次分析的间隔（秒）
+    pub fn new(
+        settings: &OnsetSettings,
+        edges: &[(f32, f32)],
+        full_scale: f32,
+        interval: f32,
+    ) -> Self {
+        let groups: Vec<usize> = edges
+            .iter()
+            .map(|&(low, high)| {
+                let center = (low * high).sqrt();
+                if center < LOW_MID_CROSSOVER {
+                    OnsetBand::Low as usize
+                } else if center < MID_HIGH_CROSSOVER {
+                    OnsetBand::Mid as usize
+                } else {
+                    OnsetBand::High as usize
+                }
+            })
+            .collect();
+        let mut group_sizes = [edges.len(), 0, 0, 0];
+        for &group in &groups {
+            group_sizes[group] += 1;
+        }
+        let mut detector = Self {
+            settings: settings.clone(),
+            full_scale,
+            groups,
+            group_sizes,
+            previous: vec![0.0; edges.len()],
+            current: vec![0.0; edges.len()],
+            history: Vec::new(),
+            history_pos: 0,
+            history_len: 0,
+            above: [false; 4],
+            state: OnsetState::default(),
+        };
+        detector.set_interval(interval);
+        detector
+    }
+
+    /// 按新的分析间隔（秒）调整阈值统计的历史长度
+    pub fn set_interval(&mut self, interval: f32) {
+        let len = ((HISTORY_SECS / interval.max(1e-6)).ceil() as usize).max(2);
+        self.history = vec![[0.0; 4]; len];
+        self.history_pos = 0;
+        self.history_len = 0;
+    }
+
+    /// 处理一次分析的频段幅值（各声道依次排列），`time` 为本次分析的时刻（秒）
+    pub fn process(&mut self, levels: &[f32], time: f64) -> &OnsetState {
+        // 各声道取平均后做对数压缩
+        let bands = self.current.len();
+        let channels = (levels.len() / bands.max(1)).max(1);
+        self.current.fill(0.0);
+        for (i, &level) in levels.iter().enumerate() {
+            self.current[i % bands] += level / channels as f32;
+        }
+        for value in &mut self.current {
+            *value = (1.0 + COMPRESSION * *value / self.full_scale).ln();
+        }
+
+        // 各频带的正向差分之和，按频段数取平均
+        let mut flux = [0.0f32; 4];
+        for ((&current, &previous), &group) in
+            self.current.iter().zip(&self.previous).zip(&self.groups)
+        {
+            let rise = (current - previous).max(0.0);
+            flux[0] += rise;
+            flux[group] += rise;
+        }
+        for (value, &size) in flux.iter_mut().zip(&self.group_sizes) {
+            *value /= size.max(1) as f32;
+        }
+        std::mem::swap(&mut self.previous, &mut self.current);
+
+        // 自适应阈值：历史谱通量的均值加若干倍标准差
+        let min_interval = self.settings.min_interval_ms as f64 / 1000.0;
+        for band in 0..4 {
+            let count = self.history_len.max(1) as f32;
+            let history = &self.history[..self.history_len];
+            let mean = history.iter().map(|h| h[band]).sum::<f32>() / count;
+            let variance = history
+                .iter()
+                .map(|h| (h[band] - mean).powi(2))
+                .sum::<f32>()
+                / count;
+            let threshold = mean + self.settings.sensitivity * variance.sqrt() + 1e-4;
+            let above = flux[band] > threshold;
+            let ready = self.state.last[band].is_none_or(|last| time - last.time >= min_interval);
+            // 只在谱通量自下而上越过阈值时触发，避免一次起音被重复计数
+            if above && !self.above[band] && ready && self.history_len == self.history.len() {
+                self.state.last[band] = Some(OnsetEvent {
+                    time,
+                    strength: flux[band] / threshold,
+                });
+            }
+            self.above[band] = above;
+        }
+        self.history[self.history_pos] = flux;
+        self.history_pos = (self.history_pos + 1) % self.history.len();
+        self.history_len = (self.history_len + 1).min(self.history.len());
+        self.state.flux = flux;
+        &self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: f32 = 0.01; // 分析间隔（秒），阈值历史为100次分析
+    const EDGES: [(f32, f32); 3] = [(50.0, 100.0), (500.0, 1000.0), (5000.0, 10000.0)]; // 低、中、高各一个频段
+
+    fn detector(min_interval_ms: f32) -> OnsetDetector {
+        let settings = OnsetSettings {
+            sensitivity: 1.5,
+            min_interval_ms,
+        };
+        OnsetDetector::new(&settings, &EDGES, 1.0, INTERVAL)
+    }
+
+    /// 依次处理 `frames` 次分析，`levels(i)` 给出第i次的频段幅值；
+    /// 返回各频带新触发起音的分析下标
+    fn onsets(
+        detector: &mut OnsetDetector,
+        frames: usize,
+        levels: impl Fn(usize) -> [f32; 3],
+    ) -> [Vec<usize>; 4] {
+        let mut found: [Vec<usize>; 4] = Default::default();
+        let mut last = [None; 4];
+        for i in 0..frames {
+            let state = detector.process(&levels(i), i as f64 * INTERVAL as f64);
+            for band in 0..4 {
+                if state.last[band] != last[band] {
+                    found[band].push(i);
+                    last[band] = state.last[band];
+                }
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn fires_once_per_rising_edge_after_history_fills() {
+        let mut detector = detector(100.0);
+        // 第50次的脉冲在历史填满前，不触发；第200次起低频持续升高，只在上升沿触发一次
+        let found = onsets(&mut detector, 300, |i| match i {
+            50 => [0.5, 0.5, 0.5],
+            200.. => [0.5, 0.0, 0.0],
+            _ => [0.0; 3],
+        });
+        assert_eq!(found[OnsetBand::Overall as usize], [200]);
+        assert_eq!(found[OnsetBand::Low as usize], [200]);
+        assert!(found[OnsetBand::Mid as usize].is_empty());
+        assert!(found[OnsetBand::High as usize].is_empty());
+    }
+
+    #[test]
+    fn respects_min_interval() {
+        let mut detector = detector(100.0);
+        // 第205次距第200次仅50 ms，被最小间隔抑制；第230次距上次300 ms，再次触发
+        let found = onsets(&mut detector, 300, |i| match i {
+            200 | 205 | 230 => [0.0, 0.0, 0.5],
+            _ => [0.0; 3],
+        });
+        assert_eq!(found[OnsetBand::High as usize], [200, 230]);
+        assert!(found[OnsetBand::Low as usize].is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+//! 滑动窗口环形缓冲区
+//!
+//! 连续累积采样，每累计一个跳跃步长（hop）就取出最新的N个采样
+//! 交给频谱分析，使分析速率固定、相邻分析窗口按 N - hop 重叠。
+//! 跳跃步长大于N时不再重叠，两次分析之间多出的采样被跳过，分析间隔仍为hop
+
+/// 固定跳跃步长的滑动窗口
+pub struct RingBuffer {
+    buffer: Vec<f32>, // 环形存储区，长度等于窗口大小
+    write_pos: usize, // 下一个写入位置
+    hop: usize,       // 跳跃步长（采样数）
+    since_hop: usize, // 距上次输出窗口后新写入的采样数
+    window: Vec<f32>, // 按时间顺序展开的最新窗口
+}
+
+impl RingBuffer {
+    /// 创建窗口大小为 `size`、跳跃步长为 `hop` 的滑动窗口
+    pub fn new(size: usize, hop: usize) -> Self {
+        Self {
+            buffer: vec![0.0; size],
+            write_pos: 0,
+            // 不按窗口大小截断，保证分析间隔与 `AnalyzerSettings::hop` 一致
+            hop: hop.max(1),
+            since_hop: 0,
+            window: vec![0.0; size],
+        }
+    }
+
+    /// 写入一批采样
+    ///
+    /// 每累计满一个跳跃步长，就以最新窗口（按时间顺序）调用一次 `on_window`
+    pub fn push(&mut self, mut samples: &[f32], mut on_window: impl FnMut(&mut [f32])) {
+        let size = self.buffer.len();
+        while !samples.is_empty() {
+            // 本轮最多写到跳跃边界，确保每个跳跃位置都被分析
+            let take = (self.hop - self.since_hop).min(samples.len());
+            for &sample in &samples[..take] {
+                self.buffer[self.write_pos] = sample;
+                self.write_pos = (self.write_pos + 1) % size;
+            }
+            samples = &samples[take..];
+            self.since_hop += take;
+            if self.since_hop == self.hop {
+                self.since_hop = 0;
+                // 写入位置之后是最旧的数据，依次展开为时间顺序
+                let (newer, older) = self.buffer.split_at(self.write_pos);
+                self.window[..older.len()].copy_from_slice(older);
+                self.window[older.len()..].copy_from_slice(newer);
+                on_window(&mut self.window);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 返回每次输出的窗口
+    fn windows(size: usize, hop: usize, samples: usize) -> Vec<Vec<f32>> {
+        let mut ring = RingBuffer::new(size, hop);
+        let input: Vec<f32> = (1..=samples).map(|i| i as f32).collect();
+        let mut windows = Vec::new();
+        // 分批写入，检验跨批次的跳跃计数
+        for chunk in input.chunks(3) {
+            ring.push(chunk, |window| windows.push(window.to_vec()));
+        }
+        windows
+    }
+
+    #[test]
+    fn overlapping_windows() {
+        let windows = windows(4, 2, 8);
+        assert_eq!(windows.len(), 4);
+        assert_eq!(windows[1], [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(windows[3], [5.0, 6.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    fn hop_larger_than_window_keeps_interval() {
+        // 每10个采样分析一次，窗口为最新的4个
+        let windows = windows(4, 10, 30);
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0], [7.0, 8.0, 9.0, 10.0]);
+        assert_eq!(windows[2], [27.0, 28.0, 29.0, 30.0]);
+    }
+}
@@ -0,0 +1,173 @@
+//! 频段刻度模块
+//!
+//! 把 [最低频率, 最高频率] 区间划分为若干频段，返回每个频段的上下边界频率。
+//! 除对数刻度外，还提供Mel、Bark、ERB等心理声学刻度、线性刻度，
+//! 以及按ISO 266/IEC 61260中心频率划分的倍频程与分数倍频程频段
+
+use std::str::FromStr;
+
+type ScaleFn = fn(f32) -> f32; // 频率与刻度值之间的映射
+
+/// 频段刻度
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BandScale {
+    Log,         // 对数刻度（等比划分）
+    Mel,         // Mel刻度
+    Bark,        // Bark临界频带刻度
+    Erb,         // 等效矩形带宽（ERB）刻度
+    Linear,      // 线性刻度（等差划分）
+    Octave(u32), // 1/N倍频程，N为1、3、6或12
+}
+
+impl FromStr for BandScale {
+    type Err = String;
+
+    /// 解析 `log`、`mel`、`bark`、`erb`、`linear`、`octave` 或 `octave=N`（N为1、3、6、12）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        if let Some(fraction) = s.strip_prefix("octave=") {
+            return match fraction.parse::<u32>() {
+                Ok(n @ (1 | 3 | 6 | 12)) => Ok(BandScale::Octave(n)),
+                _ => Err(format!(
+                    "无效的倍频程分数 `{}`，可选: 1, 3, 6, 12",
+                    fraction
+                )),
+            };
+        }
+        match s.as_str() {
+            "log" => Ok(BandScale::Log),
+            "mel" => Ok(BandScale::Mel),
+            "bark" => Ok(BandScale::Bark),
+            "erb" => Ok(BandScale::Erb),
+            "linear" => Ok(BandScale::Linear),
+            "octave" => Ok(BandScale::Octave(1)),
+            _ => Err(format!(
+                "未知的频段刻度 `{}`，可选: log, mel, bark, erb, linear, octave[=1|3|6|12]",
+                s
+            )),
+        }
+    }
+}
+
+/// 计算各频段的边界频率 `(下限, 上限)`，按频率升序排列
+///
+/// 连续刻度把区间等分为 `bands` 份；倍频程刻度的频段数由频率范围决定，
+/// 忽略 `bands`
+pub fn band_edges(scale: BandScale, min_freq: f32, max_freq: f32, bands: usize) -> Vec<(f32, f32)> {
+    let (to_scale, from_scale): (ScaleFn, ScaleFn) = match scale {
+        BandScale::Log => (f32::log10, |v| 10_f32.powf(v)),
+        BandScale::Mel => (hz_to_mel, mel_to_hz),
+        BandScale::Bark => (hz_to_bark, bark_to_hz),
+        BandScale::Erb => (hz_to_erb, erb_to_hz),
+        BandScale::Linear => (|f| f, |v| v),
+        BandScale::Octave(fraction) => return octave_edges(fraction, min_freq, max_freq),
+    };
+    let low = to_scale(min_freq);
+    let range = to_scale(max_freq) - low;
+    (0..bands)
+        .map(|i| {
+            let start = from_scale(low + range * (i as f32 / bands as f32));
+            let end = from_scale(low + range * ((i + 1) as f32 / bands as f32));
+            (start, end)
+        })
+        .collect()
+}
+
+/// 常Q频段：中心频率从 `min_freq` 起每倍频程 `bins_per_octave` 个，直到 `max_freq`
+///
+/// 频段边界为中心频率 × 2^(±1/2B)，相邻频段首尾相接
+pub fn constant_q_edges(bins_per_octave: u32, min_freq: f32, max_freq: f32) -> Vec<(f32, f32)> {
+    let b = bins_per_octave as f32;
+    let count = (b * (max_freq / min_freq).log2()).floor() as i32 + 1;
+    let half_band = 2_f32.powf(1.0 / (2.0 * b));
+    (0..count.max(0))
+        .map(|k| {
+            let center = min_freq * 2_f32.powf(k as f32 / b);
+            (center / half_band, center * half_band)
+        })
+        .collect()
+}
+
+/// ISO中心频率的1/N倍频程频段
+///
+/// 采用以2为底的定义（IEC 61260）：N为奇数时中心频率为 1000 × 2^(k/N)，
+/// N为偶数时为 1000 × 2^((2k+1)/2N)；频段边界为中心频率 × 2^(±1/2N)。
+/// 中心频率在区间外半个频段以内的也包含在内，使20 Hz–20 kHz对应标准的31个1/3倍频程
+fn octave_edges(fraction: u32, min_freq: f32, max_freq: f32) -> Vec<(f32, f32)> {
+    let n = fraction as f32;
+    let offset = if fraction.is_multiple_of(2) { 0.5 } else { 0.0 }; // 偶数分数的中心频率偏移半个频段
+    let first = (n * (min_freq / 1000.0).log2() - 0.5 - offset).ceil() as i32;
+    let last = (n * (max_freq / 1000.0).log2() + 0.5 - offset).floor() as i32;
+    let half_band = 2_f32.powf(1.0 / (2.0 * n));
+    (first..=last)
+        .map(|k| {
+            let center = 1000.0 * 2_f32.powf((k as f32 + offset) / n);
+            (center / half_band, center * half_band)
+        })
+        .collect()
+}
+
+// Mel刻度（O'Shaughnessy）
+fn hz_to_mel(f: f32) -> f32 {
+    2595.0 * (1.0 + f / 700.0).log10()
+}
+
+fn mel_to_hz(m: f32) -> f32 {
+    700.0 * (10_f32.powf(m / 2595.0) - 1.0)
+}
+
+// Bark刻度（Traunmüller）
+fn hz_to_bark(f: f32) -> f32 {
+    26.81 * f / (1960.0 + f) - 0.53
+}
+
+fn bark_to_hz(z: f32) -> f32 {
+    1960.0 * (z + 0.53) / (26.28 - z)
+}
+
+// ERB率刻度（Glasberg & Moore）
+fn hz_to_erb(f: f32) -> f32 {
+    21.4 * (1.0 + 0.00437 * f).log10()
+}
+
+fn erb_to_hz(e: f32) -> f32 {
+    (10_f32.powf(e / 21.4) - 1.0) / 0.00437
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn band_edges_span_range_contiguously() {
+        for scale in [
+            BandScale::Log,
+            BandScale::Mel,
+            BandScale::Bark,
+            BandScale::Erb,
+            BandScale::Linear,
+        ] {
+            let edges = band_edges(scale, 20.0, 20000.0, 64);
+            assert_eq!(edges.len(), 64);
+            assert!((edges[0].0 - 20.0).abs() < 0.01, "{:?}", scale);
+            assert!((edges[63].1 - 20000.0).abs() < 1.0, "{:?}", scale);
+            for pair in edges.windows(2) {
+                assert!(pair[0].0 < pair[0].1, "{:?}", scale);
+                assert!(
+                    (pair[0].1 - pair[1].0).abs() < 1e-3 * pair[0].1,
+                    "{:?}",
+                    scale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn third_octave_edges_follow_iso_centers() {
+        let edges = band_edges(BandScale::Octave(3), 20.0, 20000.0, 0);
+        assert_eq!(edges.len(), 31);
+        // 第18个频段的中心为1 kHz
+        let (low, high) = edges[17];
+        assert!(((low * high).sqrt() - 1000.0).abs() < 1.0);
+    }
+}
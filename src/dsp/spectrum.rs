@@ -1,32 +1,92 @@
+use crate::dsp::loudness::LoudnessState;
+use crate::dsp::onset::OnsetState;
+use crate::dsp::tempo::TempoState;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
-pub const BANDS: usize = 64;
+/// 一帧频谱数据
+///
+/// 立体声分析时左右声道各一组频段，单声道时两组相同
+pub struct SpectrumFrame {
+    pub left: Vec<f32>,          // 左声道（或单声道）频段
+    pub right: Vec<f32>,         // 右声道频段
+    pub time: f64,               // 本帧分析时刻，音频流开始以来的秒数
+    pub onsets: OnsetState,      // 各频带的起音状态
+    pub tempo: TempoState,       // 速度与节拍相位
+    pub loudness: LoudnessState, // 采集线程测得的响度，未启用响度表时为初始值
+}
+
+impl SpectrumFrame {
+    pub fn new(bands: usize) -> Self {
+        Self {
+            left: vec![0.0; bands],
+            right: vec![0.0; bands],
+            time: 0.0,
+            onsets: OnsetState::default(),
+            tempo: TempoState::default(),
+            loudness: LoudnessState::default(),
+        }
+    }
+}
+
+impl Clone for SpectrumFrame {
+    fn clone(&self) -> Self {
+        Self {
+            left: self.left.clone(),
+            right: self.right.clone(),
+            time: self.time,
+            onsets: self.onsets,
+            tempo: self.tempo,
+            loudness: self.loudness,
+        }
+    }
+
+    /// 复用已有缓冲区，写入管道时不做堆分配
+    fn clone_from(&mut self, source: &Self) {
+        self.left.clone_from(&source.left);
+        self.right.clone_from(&source.right);
+        self.time = source.time;
+        self.onsets = source.onsets;
+        self.tempo = source.tempo;
+        self.loudness = source.loudness;
+    }
+}
 
 #[derive(Clone)]
 pub struct SharedPipe {
-    data: Arc<[Mutex<Vec<f32>>; 2]>, // 双缓冲
-    current: Arc<AtomicUsize>,       // 当前读取的缓冲区索引
-    version: Arc<AtomicUsize>,       // 数据版本号，用于检测是否有新数据
+    data: Arc<[Mutex<SpectrumFrame>; 2]>, // 双缓冲
+    bands: usize,                         // 每个声道的频段数
+    current: Arc<AtomicUsize>,            // 当前读取的缓冲区索引
+    version: Arc<AtomicUsize>,            // 数据版本号，用于检测是否有新数据
 }
 
 impl SharedPipe {
-    pub fn new() -> Self {
+    /// 创建每个声道 `bands` 个频段的管道
+    pub fn new(bands: usize) -> Self {
         Self {
-            data: Arc::new([Mutex::new(vec![0.0; BANDS]), Mutex::new(vec![0.0; BANDS])]),
+            data: Arc::new([
+                Mutex::new(SpectrumFrame::new(bands)),
+                Mutex::new(SpectrumFrame::new(bands)),
+            ]),
+            bands,
             current: Arc::new(AtomicUsize::new(0)),
             version: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    pub fn write(&self, new_data: &[f32]) {
+    /// 每个声道的频段数
+    pub fn bands(&self) -> usize {
+        self.bands
+    }
+
+    pub fn write(&self, new_data: &SpectrumFrame) {
         // 计算要写入的缓冲区索引（与当前读取的相反）
         let read_idx = self.current.load(Ordering::Acquire);
         let write_idx = (read_idx + 1) % 2;
 
         // 获取锁并写入数据
         if let Ok(mut guard) = self.data[write_idx].lock() {
-            guard.copy_from_slice(new_data);
+            guard.clone_from(new_data);
 
             // 原子性地切换当前读取的缓冲区
             self.current.store(write_idx, Ordering::Release);
@@ -36,7 +96,7 @@ impl SharedPipe {
         }
     }
 
-    pub fn read(&self) -> Vec<f32> {
+    pub fn read(&self) -> SpectrumFrame {
         // 获取当前读取的缓冲区索引
         let idx = self.current.load(Ordering::Acquire);
 
@@ -44,6 +104,6 @@ impl SharedPipe {
         self.data[idx]
             .lock()
             .map(|g| g.clone())
-            .unwrap_or_else(|_| vec![0.0; BANDS])
+            .unwrap_or_else(|_| SpectrumFrame::new(self.bands))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,303 @@
+//! 速度（BPM）估计与节拍相位跟踪模块
+//!
+//! 以全频带谱通量作为起音强度序列，保存最近若干秒的历史，定期对去均值后的序列做自相关，
+//! 在速度范围对应的延迟中按以120 BPM为中心的对数高斯先验加权选出节拍周期。
+//! 再用梳状求和找出最近一拍距今的位置，校正持续推进的节拍相位，
+//! 使渲染端在两次起音之间也能按节拍律动
+
+const HISTORY_SECS: f32 = 8.0; // 起音强度历史的时间长度（秒）
+const UPDATE_SECS: f32 = 0.25; // 重新估计速度的间隔（秒）
+const PRIOR_BPM: f32 = 120.0; // 速度先验的中心（BPM）
+const PRIOR_OCTAVES: f32 = 1.0; // 速度先验的标准差（倍频程）
+const BPM_SMOOTHING: f32 = 0.3; // 新估计与当前速度相近时的融合比例
+const BPM_TOLERANCE: f32 = 0.05; // 视为相近速度的相对偏差
+const PHASE_GAIN: f32 = 0.5; // 每次估计对相位误差的校正比例（再乘以置信度）
+const COMB_BEATS: usize = 4; // 梳状求和只取最近几拍，周期估计的微小误差不会累积成整帧偏移
+
+/// 速度下限允许的最小值（BPM）：历史需覆盖两个最长节拍周期才能估计
+pub const MIN_BPM: f32 = 2.0 * 60.0 / HISTORY_SECS;
+
+/// 速度设置
+#[derive(Clone, Debug)]
+pub struct TempoSettings {
+    pub min_bpm: f32, // 速度下限（BPM）
+    pub max_bpm: f32, // 速度上限（BPM）
+}
+
+/// 速度与节拍相位，随频谱帧一起发布
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TempoState {
+    pub bpm: f32,        // 当前速度（BPM），尚未估计出时为0
+    pub confidence: f32, // 置信度 [0, 1]：节拍周期处的归一化自相关
+    pub phase: f32,      // 节拍相位 [0, 1)，0为拍点
+    pub beats: u64,      // 相位推进经过的拍数，渲染端可据此发现新的一拍
+}
+
+impl TempoState {
+    /// 节拍脉冲：拍点处为1，按指数衰减，`sharpness` 越大衰减越快
+    pub fn pulse(&self, sharpness: f32) -> f32 {
+        if self.bpm > 0.0 {
+            self.confidence * (-self.phase * sharpness).exp()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 基于自相关的速度估计与节拍相位跟踪
+pub struct TempoTracker {
+    settings: TempoSettings, // 速度设置
+    interval: f32,           // 相邻两次分析的间隔（秒）
+    history: Vec<f32>,       // 起音强度的环形历史
+    history_pos: usize,      // 环形历史的下一个写入位置
+    history_len: usize,      // 环形历史中的有效数据个数
+    since_update: usize,     // 距上次估计的分析次数
+    update_every: usize,     // 每隔多少次分析估计一次
+    pending_bpm: f32,        // 与当前速度不符、等待下次估计确认的新速度
+    min_lag: usize,          // 速度上限对应的延迟（分析次数）
+    max_lag: usize,          // 速度下限对应的延迟（分析次数）
+    samples: Vec<f32>,       // 按距今由近到远展开的历史，估计时复用
+    centered: Vec<f32>,      // 去均值后的历史，估计时复用
+    scores: Vec<f32>,        // 各延迟的自相关，估计时复用
+    state: TempoState,       // 对外发布的速度状态
+}
+
+impl TempoTracker {
+    /// 创建跟踪器，`interval` 为相邻两次分析的间隔（秒）
+    pub fn new(settings: &TempoSettings, interval: f32) -> Self {
+        let mut tracker = Self {
+            settings: settings.clone(),
+            interval,
+            history: Vec::new(),
+            history_pos: 0,
+            history_len: 0,
+            since_update: 0,
+            update_every: 1,
+            pending_bpm: 0.0,
+            min_lag: 1,
+            max_lag: 1,
+            samples: Vec::new(),
+            centered: Vec::new(),
+            scores: Vec::new(),
+            state: TempoState::default(),
+        };
+        tracker.set_interval(interval);
+        tracker
+    }
+
+    /// 按新的分析间隔（秒）重建历史和估计用的缓冲区，`process` 因此不做堆分配
+    pub fn set_interval(&mut self, interval: f32) {
+        let interval = interval.max(1e-6);
+        self.interval = interval;
+        let per_minute = 60.0 / interval;
+        self.min_lag = ((per_minute / self.settings.max_bpm).floor() as usize).max(1);
+        self.max_lag = ((per_minute / self.settings.min_bpm).ceil() as usize).max(self.min_lag);
+        // 延迟向上取整后两个最长周期可能略超出历史时长，按需加长
+        let len = ((HISTORY_SECS / interval).ceil() as usize).max(2 * self.max_lag + 1);
+        self.history = vec![0.0; len];
+        self.samples = Vec::with_capacity(len);
+        self.centered = Vec::with_capacity(len);
+        // 峰值两侧各多算一个延迟，用于抛物线插值
+        self.scores = Vec::with_capacity(self.max_lag - self.min_lag + 3);
+        self.history_pos = 0;
+        self.history_len = 0;
+        self.since_update = 0;
+        self.update_every = ((UPDATE_SECS / interval).round() as usize).max(1);
+    }
+
+    /// 处理一次分析的起音强度（谱通量）
+    pub fn process(&mut self, strength: f32) -> &TempoState {
+        self.history[self.history_pos] = strength;
+        self.history_pos = (self.history_pos + 1) % self.history.len();
+        self.history_len = (self.history_len + 1).min(self.history.len());
+
+        // 按当前速度推进相位，越过1时记一拍
+        if self.state.bpm > 0.0 {
+            self.state.phase += self.interval * self.state.bpm / 60.0;
+            if self.state.phase >= 1.0 {
+                self.state.phase = self.state.phase.fract();
+                self.state.beats += 1;
+            }
+        }
+
+        self.since_update += 1;
+        if self.since_update >= self.update_every {
+            self.since_update = 0;
+            self.update();
+        }
+        &self.state
+    }
+
+    /// 由自相关重新估计速度，并用梳状求和校正相位
+    fn update(&mut self) {
+        let per_minute = 60.0 / self.interval;
+        let (min_lag, max_lag) = (self.min_lag, self.max_lag);
+        // 至少需要覆盖两个最长周期才能可靠估计
+        let len = self.history_len;
+        if len < 2 * max_lag + 1 {
+            return;
+        }
+
+        // 按距今由近到远展开历史
+        let total = self.history.len();
+        self.samples.clear();
+        self.samples
+            .extend((0..len).map(|ago| self.history[(self.history_pos + total - 1 - ago) % total]));
+        let samples = &self.samples;
+        let mean = samples.iter().sum::<f32>() / len as f32;
+        self.centered.clear();
+        self.centered.extend(samples.iter().map(|&x| x - mean));
+        let centered = &self.centered;
+        let energy = centered.iter().map(|x| x * x).sum::<f32>();
+        if energy <= f32::EPSILON {
+            self.state.confidence = 0.0;
+            return;
+        }
+
+        // 各延迟的归一化自相关（按重叠长度无偏化）
+        let autocorrelation = |lag: usize| {
+            let sum = centered[..len - lag]
+                .iter()
+                .zip(&centered[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>();
+            sum / energy * len as f32 / (len - lag) as f32
+        };
+        self.scores.clear();
+        self.scores
+            .extend((min_lag - 1..=max_lag + 1).map(|lag| autocorrelation(lag.max(1))));
+        let scores = &self.scores;
+        let prior = |lag: f32| {
+            let octaves = (per_minute / lag / PRIOR_BPM).log2() / PRIOR_OCTAVES;
+            (-0.5 * octaves * octaves).exp()
+        };
+        let Some(best) = (1..scores.len() - 1).max_by(|&a, &b| {
+            let score = |i: usize| scores[i] * prior((min_lag - 1 + i) as f32);
+            score(a).total_cmp(&score(b))
+        }) else {
+            return;
+        };
+        let correlation = scores[best];
+        if correlation <= 0.0 {
+            self.state.confidence = 0.0;
+            return;
+        }
+
+        // 抛物线插值得到小数延迟
+        let (left, right) = (scores[best - 1], scores[best + 1]);
+        let curvature = left - 2.0 * correlation + right;
+        let offset = if curvature < 0.0 {
+            (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let period = (min_lag - 1 + best) as f32 + offset;
+        let bpm = (per_minute / period).clamp(self.settings.min_bpm, self.settings.max_bpm);
+        self.state.confidence = correlation.min(1.0);
+
+        // 与当前速度相近时平滑跟随，否则需连续两次估计一致才切换
+        let close = |a: f32, b: f32| b > 0.0 && (a / b - 1.0).abs() < BPM_TOLERANCE;
+        let acquired = self.state.bpm == 0.0;
+        if close(bpm, self.state.bpm) {
+            self.state.bpm += (bpm - self.state.bpm) * BPM_SMOOTHING;
+            self.pending_bpm = 0.0;
+        } else if acquired || close(bpm, self.pending_bpm) {
+            self.state.bpm = bpm;
+            self.pending_bpm = 0.0;
+        } else {
+            self.pending_bpm = bpm;
+            return;
+        }
+
+        // 梳状求和：最近一拍距今 `beat` 次分析时，最近几拍位置的起音强度之和最大
+        let period = per_minute / self.state.bpm;
+        let beat = (0..period.ceil() as usize)
+            .max_by(|&a, &b| {
+                let comb = |start: usize| {
+                    (0..)
+                        .map(|k| (start as f32 + k as f32 * period).round() as usize)
+                        .take(COMB_BEATS)
+                        .take_while(|&ago| ago < len)
+                        .map(|ago| samples[ago])
+                        .sum::<f32>()
+                };
+                comb(a).total_cmp(&comb(b))
+            })
+            .unwrap_or(0);
+        // 第 `beat` 次分析的谱通量反映的是它之前一个步长内的起音，拍点取该步长的起点，
+        // 相位因此恰好在检测到起音的那次分析越过1
+        let measured = (beat as f32 + 1.0) / period;
+        if acquired {
+            self.state.phase = measured.min(0.999);
+            return;
+        }
+        // 在圆周上取最短方向的相位误差
+        let error = (measured - self.state.phase + 0.5).rem_euclid(1.0) - 0.5;
+        let corrected = self.state.phase + error * PHASE_GAIN * self.state.confidence;
+        // 极小的负数取模后可能恰为1
+        self.state.phase = corrected.rem_euclid(1.0) % 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: f32 = 512.0 / 48000.0; // 默认跳跃步长下的分析间隔
+
+    #[test]
+    fn tracks_impulse_train_tempo_and_phase() {
+        let settings = TempoSettings {
+            min_bpm: 60.0,
+            max_bpm: 180.0,
+        };
+        let mut tracker = TempoTracker::new(&settings, INTERVAL);
+        // 120 BPM的谱通量脉冲序列，持续12秒
+        let period = 0.5 / INTERVAL;
+        let mut next_beat = 0.0;
+        for i in 0..(12.0 / INTERVAL) as usize {
+            let strength = if i as f32 >= next_beat {
+                next_beat += period;
+                1.0
+            } else {
+                0.0
+            };
+            let state = tracker.process(strength);
+            assert!((0.0..1.0).contains(&state.phase), "相位 {}", state.phase);
+            // 锁定后，带脉冲的那次分析时相位应已越过拍点，节拍脉冲处于峰值附近
+            if strength > 0.0 && i as f32 * INTERVAL > 6.0 {
+                assert!(
+                    state.phase < 0.05,
+                    "第{}次分析: 拍点处相位 {}",
+                    i,
+                    state.phase
+                );
+            }
+        }
+        let state = tracker.process(0.0);
+        assert!((state.bpm - 120.0).abs() < 2.0, "速度 {} BPM", state.bpm);
+        assert!(state.confidence > 0.5, "置信度 {}", state.confidence);
+    }
+
+    #[test]
+    fn lowest_allowed_tempo_still_estimates() {
+        let settings = TempoSettings {
+            min_bpm: MIN_BPM,
+            max_bpm: 60.0,
+        };
+        let mut tracker = TempoTracker::new(&settings, INTERVAL);
+        // 历史填满后必须能给出估计
+        let period = 60.0 / 30.0 / INTERVAL;
+        let mut next_beat = 0.0;
+        for i in 0..tracker.history.len() + tracker.update_every {
+            let strength = if i as f32 >= next_beat {
+                next_beat += period;
+                1.0
+            } else {
+                0.0
+            };
+            tracker.process(strength);
+        }
+        assert!(tracker.state.bpm > 0.0);
+    }
+}
@@ -0,0 +1,139 @@
+//! 频率计权模块
+//!
+//! 按各频段的中心频率计算标准计权曲线的增益：A、C、Z（IEC 61672）、
+//! ITU-R 468，以及以1 kHz为基准、每倍频程固定dB的粉红噪声倾斜。
+//! 增益只取决于频率，频段数或刻度变化时显示效果保持一致
+
+use std::str::FromStr;
+
+const DEFAULT_PINK_SLOPE: f32 = 3.0; // 默认倾斜：每倍频程+3dB，粉红噪声显示为水平
+
+/// 频率计权曲线
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Weighting {
+    A,         // A计权
+    C,         // C计权
+    Z,         // Z计权（不计权）
+    Itu468,    // ITU-R 468噪声计权
+    Pink(f32), // 每倍频程固定dB的倾斜，以1 kHz为0dB
+}
+
+impl FromStr for Weighting {
+    type Err = String;
+
+    /// 解析 `a`、`c`、`z`、`itu-468`、`pink` 或 `pink=dB/oct`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        if let Some(slope) = s.strip_prefix("pink=") {
+            return slope
+                .parse::<f32>()
+                .ok()
+                .filter(|slope| slope.is_finite())
+                .map(Weighting::Pink)
+                .ok_or_else(|| format!("无效的倾斜斜率: `{}`", slope));
+        }
+        match s.as_str() {
+            "a" => Ok(Weighting::A),
+            "c" => Ok(Weighting::C),
+            "z" => Ok(Weighting::Z),
+            "itu-468" => Ok(Weighting::Itu468),
+            "pink" => Ok(Weighting::Pink(DEFAULT_PINK_SLOPE)),
+            _ => Err(format!(
+                "未知的频率计权 `{}`，可选: a, c, z, itu-468, pink[=dB/oct]",
+                s
+            )),
+        }
+    }
+}
+
+impl Weighting {
+    /// 频率 `f`（Hz）处的计权值（dB）
+    pub fn db(self, f: f32) -> f32 {
+        let f = f.max(1.0) as f64;
+        let f2 = f * f;
+        let db = match self {
+            Weighting::A => {
+                let r = 12194.0_f64.powi(2) * f2 * f2
+                    / ((f2 + 20.6_f64.powi(2))
+                        * ((f2 + 107.7_f64.powi(2)) * (f2 + 737.9_f64.powi(2))).sqrt()
+                        * (f2 + 12194.0_f64.powi(2)));
+                20.0 * r.log10() + 2.0
+            }
+            Weighting::C => {
+                let r = 12194.0_f64.powi(2) * f2
+                    / ((f2 + 20.6_f64.powi(2)) * (f2 + 12194.0_f64.powi(2)));
+                20.0 * r.log10() + 0.06
+            }
+            Weighting::Z => 0.0,
+            Weighting::Itu468 => {
+                let h1 = -4.737338981378384e-24 * f2.powi(3) + 2.043828333606125e-15 * f2 * f2
+                    - 1.363894795463638e-7 * f2
+                    + 1.0;
+                let h2 = 1.306612257412824e-19 * f2 * f2 * f - 2.118150887518656e-11 * f2 * f
+                    + 5.559488023498642e-4 * f;
+                let r = 1.246332637532143e-4 * f / (h1 * h1 + h2 * h2).sqrt();
+                18.2 + 20.0 * r.log10()
+            }
+            Weighting::Pink(slope) => slope as f64 * (f / 1000.0).log2(),
+        };
+        db as f32
+    }
+
+    /// 各频段的幅度增益，按频段几何中心频率计算
+    pub fn band_gains(self, edges: &[(f32, f32)]) -> Vec<f32> {
+        edges
+            .iter()
+            .map(|&(low, high)| 10_f32.powf(self.db((low * high).sqrt()) / 20.0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IEC 61672 / ITU-R 468 参考值（dB）
+    const REFERENCE: [(Weighting, f32, f32); 7] = [
+        (Weighting::A, 1000.0, 0.0),
+        (Weighting::A, 100.0, -19.1),
+        (Weighting::A, 10000.0, -2.5),
+        (Weighting::C, 1000.0, 0.0),
+        (Weighting::C, 31.5, -3.0),
+        (Weighting::Itu468, 1000.0, 0.0),
+        (Weighting::Itu468, 6300.0, 12.2),
+    ];
+
+    #[test]
+    fn curves_match_reference_tables() {
+        for (weighting, freq, expected) in REFERENCE {
+            let db = weighting.db(freq);
+            assert!(
+                (db - expected).abs() < 0.1,
+                "{:?} @ {} Hz: {} dB，应为 {} dB",
+                weighting,
+                freq,
+                db,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn pink_tilt_is_per_octave_around_1khz() {
+        let pink = Weighting::Pink(3.0);
+        assert!(pink.db(1000.0).abs() < 1e-4);
+        assert!((pink.db(2000.0) - 3.0).abs() < 1e-4);
+        assert!((pink.db(500.0) + 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn parses_names_and_rejects_non_finite_slope() {
+        assert_eq!("A".parse(), Ok(Weighting::A));
+        assert_eq!("itu-468".parse(), Ok(Weighting::Itu468));
+        assert_eq!("pink".parse(), Ok(Weighting::Pink(DEFAULT_PINK_SLOPE)));
+        assert_eq!("pink=4.5".parse(), Ok(Weighting::Pink(4.5)));
+        for invalid in ["pink=nan", "pink=inf", "pink=", "b"] {
+            assert!(invalid.parse::<Weighting>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! 窗函数模块
+//!
+//! 为FFT分析提供预计算的窗函数系数表，抑制矩形窗带来的频谱泄漏。
+//! 系数表已乘以幅度或能量校正因子，使加窗后的频谱幅值与不加窗时可比
+
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+const DEFAULT_KAISER_BETA: f64 = 8.6; // Kaiser窗默认β，旁瓣约-90dB
+const MAX_KAISER_BETA: f64 = 50.0; // Kaiser窗β上限，过大时贝塞尔函数溢出为inf
+
+/// 窗函数类型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowKind {
+    Rectangular,    // 矩形窗（不加窗）
+    Hann,           // 汉宁窗
+    Hamming,        // 汉明窗
+    BlackmanHarris, // 4项Blackman-Harris窗
+    FlatTop,        // 平顶窗，幅值测量误差最小
+    Kaiser(f64),    // Kaiser窗，参数为β
+}
+
+impl FromStr for WindowKind {
+    type Err = String;
+
+    /// 解析 `rectangular`、`hann`、`hamming`、`blackman-harris`、`flat-top`、
+    /// `kaiser` 或 `kaiser=β`（0 ≤ β ≤ 50）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        if let Some(beta) = s.strip_prefix("kaiser=") {
+            return beta
+                .parse::<f64>()
+                .ok()
+                .filter(|beta| (0.0..=MAX_KAISER_BETA).contains(beta))
+                .map(WindowKind::Kaiser)
+                .ok_or_else(|| format!("无效的 Kaiser β: `{}`", beta));
+        }
+        match s.as_str() {
+            "rectangular" => Ok(WindowKind::Rectangular),
+            "hann" => Ok(WindowKind::Hann),
+            "hamming" => Ok(WindowKind::Hamming),
+            "blackman-harris" => Ok(WindowKind::BlackmanHarris),
+            "flat-top" => Ok(WindowKind::FlatTop),
+            "kaiser" => Ok(WindowKind::Kaiser(DEFAULT_KAISER_BETA)),
+            _ => Err(format!(
+                "未知的窗函数 `{}`，可选: rectangular, hann, hamming, blackman-harris, flat-top, kaiser[=β]",
+                s
+            )),
+        }
+    }
+}
+
+/// 窗函数校正方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowCorrection {
+    Amplitude, // 幅度校正：保持单频信号的峰值幅度
+    Energy,    // 能量校正：保持宽带信号的总能量
+}
+
+impl FromStr for WindowCorrection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "amplitude" => Ok(WindowCorrection::Amplitude),
+            "energy" => Ok(WindowCorrection::Energy),
+            _ => Err(format!(
+                "未知的窗函数校正方式 `{}`，可选: amplitude, energy",
+                s
+            )),
+        }
+    }
+}
+
+/// 计算长度为 `size` 的窗函数系数表（已乘以校正因子）
+///
+/// 采用周期形式（分母为N），适合频谱分析
+pub fn coefficients(kind: WindowKind, size: usize, correction: WindowCorrection) -> Vec<f32> {
+    let n = size as f64;
+    // 余弦和窗：w[i] = Σ (-1)^k × a_k × cos(2πki/N)
+    let cosine_sum = |a: &[f64], i: usize| -> f64 {
+        a.iter()
+            .enumerate()
+            .map(|(k, &a_k)| {
+                let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                sign * a_k * (2.0 * PI * k as f64 * i as f64 / n).cos()
+            })
+            .sum()
+    };
+    let window: Vec<f64> = (0..size)
+        .map(|i| match kind {
+            WindowKind::Rectangular => 1.0,
+            WindowKind::Hann => cosine_sum(&[0.5, 0.5], i),
+            WindowKind::Hamming => cosine_sum(&[0.54, 0.46], i),
+            WindowKind::BlackmanHarris => cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168], i),
+            WindowKind::FlatTop => cosine_sum(
+                &[
+                    0.21557895,
+                    0.41663158,
+                    0.277263158,
+                    0.083578947,
+                    0.006947368,
+                ],
+                i,
+            ),
+            WindowKind::Kaiser(beta) => {
+                let x = 2.0 * i as f64 / n - 1.0;
+                bessel_i0(beta * (1.0 - x * x).sqrt()) / bessel_i0(beta)
+            }
+        })
+        .collect();
+    // 幅度校正因子 N/Σw，能量校正因子 sqrt(N/Σw²)
+    let gain = match correction {
+        WindowCorrection::Amplitude => n / window.iter().sum::<f64>(),
+        WindowCorrection::Energy => (n / window.iter().map(|w| w * w).sum::<f64>()).sqrt(),
+    };
+    window.iter().map(|w| (w * gain) as f32).collect()
+}
+
+/// 第一类零阶修正贝塞尔函数，用级数展开计算
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..50 {
+        term *= (half / k as f64) * (half / k as f64);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kaiser_beta_is_bounded() {
+        assert_eq!("kaiser=50".parse(), Ok(WindowKind::Kaiser(50.0)));
+        for beta in ["-1", "50.1", "800", "inf", "nan"] {
+            assert!(format!("kaiser={}", beta).parse::<WindowKind>().is_err());
+        }
+        let window = coefficients(WindowKind::Kaiser(50.0), 4096, WindowCorrection::Amplitude);
+        assert!(window.iter().all(|w| w.is_finite()));
+    }
+
+    #[test]
+    fn hann_correction_gains() {
+        const N: usize = 1024;
+        // 汉宁窗均值为1/2，均方值为3/8
+        let amplitude = coefficients(WindowKind::Hann, N, WindowCorrection::Amplitude);
+        assert!((amplitude[N / 2] - 2.0).abs() < 1e-6);
+        assert!((amplitude.iter().sum::<f32>() / N as f32 - 1.0).abs() < 1e-6);
+        let energy = coefficients(WindowKind::Hann, N, WindowCorrection::Energy);
+        assert!((energy[N / 2] - (8.0f32 / 3.0).sqrt()).abs() < 1e-6);
+        assert!((energy.iter().map(|w| w * w).sum::<f32>() / N as f32 - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn flat_top_reads_off_bin_amplitude() {
+        const N: usize = 1024;
+        let window = coefficients(WindowKind::FlatTop, N, WindowCorrection::Amplitude);
+        // 最近bin的DFT幅值，换算为相对正弦幅度的dB
+        let peak_db = |bin: f64| {
+            let k = bin.round();
+            let (re, im) = (0..N).fold((0.0f64, 0.0f64), |(re, im), i| {
+                let x = (2.0 * PI * bin * i as f64 / N as f64).sin() * window[i] as f64;
+                let phase = 2.0 * PI * k * i as f64 / N as f64;
+                (re + x * phase.cos(), im - x * phase.sin())
+            });
+            20.0 * ((re * re + im * im).sqrt() / (N as f64 / 2.0)).log10()
+        };
+        for offset in [0.0, 0.25, 0.5] {
+            let db = peak_db(100.0 + offset);
+            assert!(db.abs() < 0.1, "偏离bin {}: {} dB", offset, db);
+        }
+    }
+
+    #[test]
+    fn blackman_harris_is_symmetric() {
+        const N: usize = 1024;
+        let window = coefficients(WindowKind::BlackmanHarris, N, WindowCorrection::Amplitude);
+        // 周期形式关于N/2对称：w[i] = w[N-i]
+        for i in 1..N {
+            assert!((window[i] - window[N - i]).abs() < 1e-6, "第{}个系数", i);
+        }
+        assert_eq!(
+            (0..N).max_by(|&a, &b| window[a].total_cmp(&window[b])),
+            Some(N / 2)
+        );
+    }
+}
@@ -4,7 +4,7 @@
 //! 主要功能包括：音频捕获、频谱分析、实时渲染
 //!
 //! 程序架构：
-//! - 音频模块：负责Windows系统音频捕获
+//! - 音频模块：负责系统音频捕获（Windows使用WASAPI，Linux使用PulseAudio/PipeWire或ALSA）
 //! - DSP模块：处理音频信号的频谱分析
 //! - 可视化模块：使用WGPU进行实时图形渲染
 //!
@@ -12,106 +12,186 @@
 
 // 声明模块
 mod audio; // 音频捕获模块
+mod config; // 命令行配置模块
 mod dsp; // 数字信号处理模块
 mod viz; // 可视化渲染模块
 // 导入必要的模块和类型
+use crate::audio::channel_map::ChannelMap; // 声道映射
+use crate::audio::pipe::PipeSource; // 字节流音频源
+use crate::audio::{AudioSource, StreamFormat}; // 音频源接口
+use crate::config::Config; // 运行配置
+use crate::dsp::calibrate::{Calibrator, Profile}; // 频段校准
+use crate::dsp::fft::{AnalyzerSettings, SpectrumAnalyzer}; // 频谱分析器
+use crate::dsp::loudness::LoudnessMeter; // 响度测量
+use crate::dsp::ring::RingBuffer; // 滑动窗口缓冲区
 use crate::dsp::spectrum::SharedPipe; // 频谱数据共享管道
-use crate::viz::viz::run; // 可视化渲染入口函数
-use rustfft::{FftPlanner, num_complex::Complex}; // FFT计算相关
-use windows::Win32::Media::Audio::AUDCLNT_BUFFERFLAGS_SILENT; // 音频静音标志
+use crate::viz::render::run; // 可视化渲染入口函数
+use anyhow::Result;
+use clap::Parser; // 命令行解析
 
-// 全局常量定义
-const FFT_SIZE: usize = 4096; // FFT计算的采样点数，影响频率分辨率
 /// 程序主入口函数
 ///
 /// 程序采用双线程架构：
 /// 1. 音频处理线程：负责音频捕获和频谱分析
 /// 2. 渲染主线程：负责图形界面和可视化渲染
 fn main() {
+    // 解析命令行参数
+    let config = Config::parse();
+    config.validate();
+    let settings = config.analyzer_settings();
+    // 启动前加载校准档案，名称或内容有误时直接退出
+    let profile = match config.profile.as_deref().map(Profile::load).transpose() {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("加载校准档案失败: {:?}", e);
+            return;
+        }
+    };
+
     // 创建频谱数据共享管道，用于线程间通信
-    let spectrum = SharedPipe::new();
+    let spectrum = SharedPipe::new(settings.band_count());
     let audio_spectrum = spectrum.clone(); // 克隆句柄供音频线程使用
 
     // 启动音频处理线程
+    let split_stereo = config.split_stereo; // 渲染线程使用的显示选项
+    let loudness_meter = config.loudness_meter;
+    let peaks = config.peak_settings();
     std::thread::spawn(move || {
-        // 尝试初始化音频捕获
-        match audio::capture::capture() {
-            Ok(capture_client) => {
-                println!("capture successfully");
-
-                // 初始化FFT规划器和相关缓冲区
-                let mut planner = FftPlanner::new(); // FFT规划器
-                let fft = planner.plan_fft_forward(FFT_SIZE); // 前向FFT计划
-                let mut samples = vec![0.0f32; FFT_SIZE]; // 音频采样缓冲区
-                let mut fft_input = vec![Complex::new(0.0, 0.0); FFT_SIZE]; // FFT输入缓冲区
-                // 音频处理主循环
-                loop {
-                    // 检查是否有新的音频数据包
-                    match unsafe { capture_client.GetNextPacketSize() } {
-                        Ok(packet_length) => {
-                            // 如果有数据需要处理
-                            if packet_length > 0 {
-                                // 准备接收音频数据的变量
-                                let mut data_ptr: *mut u8 = std::ptr::null_mut(); // 数据指针
-                                let mut num_frames: u32 = 0; // 帧数
-                                let mut flags: u32 = 0; // 状态标志
-                                // 获取音频缓冲区数据
-                                match unsafe {
-                                    capture_client.GetBuffer(
-                                        &mut data_ptr,   // 输出数据指针
-                                        &mut num_frames, // 输出帧数
-                                        &mut flags,      // 输出状态标志
-                                        None,            // 不需要时间戳
-                                        None,            // 不需要设备位置
-                                    )
-                                } {
-                                    Ok(_) => {
-                                        // 检查是否为有效音频数据（非静音）
-                                        if (flags & (AUDCLNT_BUFFERFLAGS_SILENT.0 as u32)) == 0 {
-                                            // 将原始字节数据转换为浮点数采样数据
-                                            let raw_samples: &[f32] = unsafe {
-                                                std::slice::from_raw_parts(
-                                                    data_ptr as *const f32,    // 强制转换为f32指针
-                                                    (num_frames * 2) as usize, // 立体声数据，每帧2个样本
-                                                )
-                                            };
-                                            // 将原始采样数据复制到处理缓冲区
-                                            for (i, &sample) in
-                                                raw_samples.iter().enumerate().take(FFT_SIZE)
-                                            {
-                                                samples[i] = sample;
-                                            }
-                                            // 执行频谱分析
-                                            dsp::fft::run_fft(
-                                                &mut samples,    // 输入采样数据
-                                                &mut fft_input,  // FFT输入缓冲区
-                                                &*fft,           // FFT计算计划
-                                                &audio_spectrum, // 输出频谱管道
-                                            );
-                                        }
-                                        // 释放音频缓冲区
-                                        let _ = unsafe { capture_client.ReleaseBuffer(num_frames) };
-                                    }
-                                    Err(e) => {
-                                        eprintln!("获取音频缓冲区失败: {:?}", e);
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("获取数据包大小失败: {:?}", e);
-                        }
-                    }
-                    // 短暂休眠以控制采样频率
-                    std::thread::sleep(std::time::Duration::from_micros(200));
-                }
+        // 尝试初始化音频输入
+        match open_source(&config) {
+            Ok(mut source) => {
+                println!("capture successfully: {:?}", source.format());
+                let channel_map = ChannelMap::new(&config.channel_map, &source.format());
+                println!(
+                    "channel map: {:?} -> {} ch",
+                    config.channel_map,
+                    channel_map.out_channels()
+                );
+                process_audio(
+                    source.as_mut(),
+                    &channel_map,
+                    &settings,
+                    &audio_spectrum,
+                    config.loudness_meter,
+                    profile.as_ref(),
+                    config
+                        .calibrate
+                        .as_deref()
+                        .map(|name| (name, config.calibrate_secs)),
+                );
             }
             Err(e) => {
                 eprintln!("capture fn failed: {:?}", e);
             }
         }
     });
-    run(spectrum);
+    run(spectrum, split_stereo, loudness_meter, peaks);
+}
+
+/// 根据配置打开音频源
+///
+/// 指定了输入文件时读取WAV文件，指定了标准输入时读取原始PCM，否则捕获系统音频
+fn open_source(config: &Config) -> Result<Box<dyn AudioSource>> {
+    if let Some(path) = &config.input {
+        return Ok(Box::new(audio::wav::open(path)?));
+    }
+    if config.stdin {
+        let format = StreamFormat::new(config.channels, config.rate, config.format.sample_type());
+        println!("STAGE 1: Reading stdin, format is: {:?}", format);
+        return Ok(Box::new(PipeSource::new(std::io::stdin(), format)));
+    }
+    audio::open_default()
+}
+
+/// 音频处理主循环
+///
+/// 从音频源持续拉取数据，经声道映射后写入滑动窗口，
+/// 每累计一个跳跃步长的帧对最新窗口执行一次频谱分析，结果写入共享管道。
+/// `loudness_meter` 为真时测量映射前全部声道的响度，随频谱帧发布，否则帧中响度保持初始值。
+/// 指定 `profile` 时以档案作为频段增益；指定 `calibration`（档案名, 秒数）时
+/// 先测量这段时长的各频段电平，保存为档案后立即应用
+fn process_audio(
+    source: &mut dyn AudioSource,
+    channel_map: &ChannelMap,
+    settings: &AnalyzerSettings,
+    spectrum: &SharedPipe,
+    loudness_meter: bool,
+    profile: Option<&Profile>,
+    calibration: Option<(&str, f32)>,
+) {
+    let channels = channel_map.out_channels(); // 参与分析的声道数
+    let sample_rate = source.format().sample_rate;
+    let mut analyzer = SpectrumAnalyzer::new(settings, sample_rate, channels);
+    if settings.max_freq > sample_rate as f32 / 2.0 {
+        eprintln!(
+            "采样率 {} Hz 的奈奎斯特频率低于 --max-freq {} Hz，超出部分的频段没有数据",
+            sample_rate, settings.max_freq
+        );
+    }
+    if let Some(profile) = profile {
+        analyzer.set_band_gains(profile.band_gains(analyzer.band_edges()));
+    }
+    // 校准时长换算为分析次数
+    let mut calibrator = calibration.map(|(name, secs)| {
+        println!("calibrating profile `{}` for {} s...", name, secs);
+        let windows = (secs * sample_rate as f32 / settings.hop as f32).ceil() as usize;
+        (name, Calibrator::new(settings.band_count(), windows))
+    });
+    // 跳跃步长按帧配置，换算为映射后的交错采样数
+    let hop = settings.hop * channels;
+    let mut ring = RingBuffer::new(analyzer.window_len(), hop); // 滑动窗口采样缓冲区
+    // 对映射前的全部声道测量响度，未显示响度表时不测量
+    let mut loudness = loudness_meter.then(|| LoudnessMeter::new(&source.format()));
+    let mut frames = Vec::new(); // 音频源读取缓冲区
+    let mut mapped = Vec::new(); // 声道映射后的采样
+    loop {
+        frames.clear();
+        match source.read(&mut frames) {
+            Ok(0) => {
+                // 暂无数据，短暂休眠后再次轮询
+                std::thread::sleep(std::time::Duration::from_micros(200));
+            }
+            Ok(_) => {
+                let format = source.format();
+                if let Some(loudness) = &mut loudness {
+                    loudness.process(&format, &frames);
+                    analyzer.set_loudness(*loudness.state());
+                }
+                // 按配置映射为单声道或立体声
+                mapped.clear();
+                channel_map.apply(&frames, &mut mapped);
+                // 采样率变化时重新划分频段
+                analyzer.set_sample_rate(format.sample_rate);
+                // 每个跳跃位置执行一次频谱分析
+                ring.push(&mapped, |samples| {
+                    spectrum.write(analyzer.process(samples));
+                    // 校准期间累积频段电平，达到时长后保存并应用档案
+                    if calibrator
+                        .as_mut()
+                        .is_some_and(|(_, calibrator)| calibrator.add(analyzer.levels()))
+                        && let Some((name, calibrator)) = calibrator.take()
+                    {
+                        finish_calibration(&mut analyzer, name, &calibrator);
+                    }
+                });
+            }
+            Err(e) => {
+                // 音频源已失效或数据已读完，结束音频线程
+                eprintln!("读取音频数据失败: {:?}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// 根据校准结果保存命名档案，并把增益应用到分析器
+fn finish_calibration(analyzer: &mut SpectrumAnalyzer, name: &str, calibrator: &Calibrator) {
+    let profile = Profile::new(analyzer.band_edges(), &calibrator.gains_db());
+    match profile.save(name) {
+        Ok(path) => println!("calibration profile saved: {}", path.display()),
+        Err(e) => eprintln!("保存校准档案失败: {:?}", e),
+    }
+    analyzer.set_band_gains(profile.band_gains(analyzer.band_edges()));
 }
 
 // ===========================================================================
@@ -1 +1,2 @@
-pub mod viz;
+pub mod peak;
+pub mod render;
@@ -0,0 +1,100 @@
+//! 峰值保持模块
+//!
+//! 每个频谱柱上方有一个峰值帽：柱高超过帽时帽被顶起，之后保持一段时间，
+//! 再以恒定加速度下落，直到落回柱顶。按真实经过的时间推进，与渲染帧率无关
+
+/// 峰值帽设置
+#[derive(Clone, Copy, Debug)]
+pub struct PeakSettings {
+    pub hold_ms: f32, // 被顶起后的保持时间（毫秒）
+    pub gravity: f32, // 下落加速度（满柱高 / 秒²）
+}
+
+/// 各频段的峰值帽
+pub struct PeakHold {
+    settings: PeakSettings, // 峰值帽设置
+    heights: Vec<f32>,      // 帽的高度，与柱高同一量纲
+    holds: Vec<f32>,        // 剩余保持时间（秒）
+    velocities: Vec<f32>,   // 下落速度（满柱高 / 秒）
+}
+
+impl PeakHold {
+    /// 为 `bands` 个频段创建峰值帽
+    pub fn new(settings: PeakSettings, bands: usize) -> Self {
+        Self {
+            settings,
+            heights: vec![0.0; bands],
+            holds: vec![0.0; bands],
+            velocities: vec![0.0; bands],
+        }
+    }
+
+    /// 按当前柱高推进 `dt` 秒，返回各帽的高度
+    pub fn update(&mut self, values: &[f32], dt: f32) -> &[f32] {
+        let hold_secs = self.settings.hold_ms / 1000.0;
+        for (((&value, height), hold), velocity) in values
+            .iter()
+            .zip(&mut self.heights)
+            .zip(&mut self.holds)
+            .zip(&mut self.velocities)
+        {
+            if value >= *height {
+                *height = value;
+                *hold = hold_secs;
+                *velocity = 0.0;
+                continue;
+            }
+            // 保持时间用完后，剩余的时间用于下落
+            let mut fall = dt;
+            if *hold > 0.0 {
+                fall = (dt - *hold).max(0.0);
+                *hold = (*hold - dt).max(0.0);
+            }
+            if fall > 0.0 {
+                // 匀加速运动的位移，不受每帧时长影响
+                *height -= *velocity * fall + 0.5 * self.settings.gravity * fall * fall;
+                *velocity += self.settings.gravity * fall;
+                if *height <= value {
+                    *height = value;
+                    *velocity = 0.0;
+                }
+            }
+        }
+        &self.heights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: PeakSettings = PeakSettings {
+        hold_ms: 500.0,
+        gravity: 4.0,
+    };
+
+    #[test]
+    fn update_is_frame_rate_independent() {
+        // 帽被顶到1后柱高落到0，经过0.7秒：一次推进与分成n次推进的结果相同，
+        // 其中跨过0.5秒的保持结束点
+        let mut once = PeakHold::new(SETTINGS, 1);
+        once.update(&[1.0], 0.0);
+        let expected = once.update(&[0.0], 0.7)[0];
+        assert!(expected > 0.0 && expected < 1.0);
+        for steps in [2, 3, 7, 60, 144] {
+            let mut stepped = PeakHold::new(SETTINGS, 1);
+            stepped.update(&[1.0], 0.0);
+            let mut height = 0.0;
+            for _ in 0..steps {
+                height = stepped.update(&[0.0], 0.7 / steps as f32)[0];
+            }
+            assert!(
+                (height - expected).abs() < 1e-4,
+                "{} 步: {} != {}",
+                steps,
+                height,
+                expected
+            );
+        }
+    }
+}
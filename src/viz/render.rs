@@ -1,17 +1,22 @@
 //! WGPU可视化渲染模块
 //!
 //! 该模块使用WGPU图形API实现音频频谱的实时可视化渲染
-//! 采用winit创建窗口，实现64个频段的柱状图显示效果
+//! 采用winit创建窗口，按频谱管道的频段数绘制柱状图
 //!
 //! 主要特性：
 //! - 实时频谱柱状图渲染
-//! - 平滑动画效果
 //! - 响应式窗口大小调整
-//! - 中心水平线装饰效果
+//! - 中心水平线装饰效果，随起音和节拍脉动
+//! - 可选的EBU R128响度表
+//! - 可选的峰值保持帽，保持后按重力加速下落
 // 导入必要的crate和模块
-use crate::dsp::spectrum::{BANDS, SharedPipe}; // 频谱数据相关
+use crate::dsp::loudness::LoudnessState; // 响度测量结果
+use crate::dsp::onset::OnsetBand; // 起音检测频带
+use crate::dsp::spectrum::SharedPipe; // 频谱数据相关
+use crate::viz::peak::{PeakHold, PeakSettings}; // 峰值帽
 use pollster::block_on; // 异步运行时阻塞执行
 use std::mem::size_of; // 内存大小计算
+use std::time::Instant; // 重绘间隔计时
 // WGPU图形API相关导入
 use wgpu::{
     BlendState, Color, ColorTargetState, ColorWrites, CommandEncoderDescriptor, CompositeAlphaMode,
@@ -29,6 +34,26 @@ use winit::{
     event_loop::{ActiveEventLoop, EventLoop},     // 事件循环
     window::{Window, WindowAttributes, WindowId}, // 窗口相关类型
 };
+/// 每个频谱柱的顶点数：上下两个矩形各6个，加上中心装饰线6个
+const VERTICES_PER_BAR: usize = 18;
+/// 起音脉动的衰减时间常数（秒）
+const ONSET_PULSE_SECS: f32 = 0.12;
+/// 节拍脉动在一拍内的衰减速度
+const BEAT_PULSE_SHARPNESS: f32 = 6.0;
+/// 柱高上限（归一化设备坐标），上下两组柱状图各占半个窗口
+const MAX_BAR_HEIGHT: f32 = 0.5;
+/// 峰值帽与柱顶的间隙
+const CAP_GAP: f32 = 0.005;
+/// 峰值帽的厚度
+const CAP_THICKNESS: f32 = 0.012;
+/// 每个频谱柱的峰值帽顶点数：上下各一个矩形
+const CAP_VERTICES_PER_BAR: usize = 12;
+/// 推进峰值帽时单帧时长的上限（秒），窗口被拖动或遮挡后恢复时帽不会瞬间落底
+const MAX_FRAME_SECS: f32 = 0.1;
+/// 响度表的顶点数：瞬时、短期、真峰值三条横条，综合响度刻线，以及7条刻度线
+const METER_VERTICES: usize = 11 * 6;
+/// 响度表的显示范围（LUFS / dBTP）
+const METER_MIN_DB: f32 = -60.0;
 /// 顶点数据结构
 ///
 /// 表示2D图形的顶点位置信息
@@ -51,24 +76,61 @@ impl Vertex {
         }
     }
 }
+/// 窗口标题
+const TITLE: &str = "Explore Demo";
+
+/// 每帧顶点数上限
+fn max_vertices(bars: usize, loudness_meter: bool, peak_caps: bool) -> usize {
+    let per_bar = VERTICES_PER_BAR + if peak_caps { CAP_VERTICES_PER_BAR } else { 0 };
+    bars * per_bar + if loudness_meter { METER_VERTICES } else { 0 }
+}
+
+/// 轴对齐矩形的两个三角形
+fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> [Vertex; 6] {
+    [[x0, y0], [x1, y0], [x1, y1], [x0, y0], [x1, y1], [x0, y1]].map(|position| Vertex { position })
+}
+
+/// 生成窗口顶部的响度表
+///
+/// 横向把 -60～0 LUFS（真峰值为dBTP）映射到 [-0.9, 0.9]，
+/// 自上而下依次为瞬时响度、短期响度和真峰值，竖线标出综合响度，底部每10 dB一条刻度
+fn loudness_meter_vertices(loudness: &LoudnessState, vertices: &mut Vec<Vertex>) {
+    let x = |db: f32| {
+        let t = if db.is_finite() {
+            ((db - METER_MIN_DB) / -METER_MIN_DB).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        -0.9 + 1.8 * t
+    };
+    vertices.extend_from_slice(&rect(-0.9, 0.88, x(loudness.momentary), 0.92));
+    vertices.extend_from_slice(&rect(-0.9, 0.82, x(loudness.short_term), 0.86));
+    vertices.extend_from_slice(&rect(-0.9, 0.77, x(loudness.true_peak), 0.79));
+    let integrated = x(loudness.integrated);
+    vertices.extend_from_slice(&rect(integrated - 0.003, 0.81, integrated + 0.003, 0.93));
+    for tick in 0..=6 {
+        let tick = x(METER_MIN_DB + 10.0 * tick as f32);
+        vertices.extend_from_slice(&rect(tick - 0.002, 0.74, tick + 0.002, 0.755));
+    }
+}
+
 /// 启动可视化渲染
 ///
 /// 初始化WGPU渲染环境并启动主渲染循环
 ///
 /// # 参数
 /// * `shared` - 频谱数据共享管道
-pub fn run(shared: SharedPipe) {
+/// * `split_stereo` - 上方柱状图显示左声道、下方镜像柱状图显示右声道
+/// * `loudness_meter` - 在窗口顶部显示响度表，并在标题栏显示各项读数
+/// * `peaks` - 峰值帽设置，为None时不显示峰值帽
+pub fn run(
+    shared: SharedPipe,
+    split_stereo: bool,
+    loudness_meter: bool,
+    peaks: Option<PeakSettings>,
+) {
     // 使用pollster阻塞执行异步代码
     block_on(async move {
-        // 初始化频谱平滑数据
-        // let mut smooth_bands = vec![0.0f32; BANDS];  // 平滑后的频段数据
-        // const SMOOTHING: f32 = 0.2;                  // 平滑系数
-        // let raw = shared.read();                     // 读取初始频谱数据
-        //
-        // // 应用初始平滑处理
-        // for i in 0..BANDS {
-        //     smooth_bands[i] = smooth_bands[i] * (1.0 - SMOOTHING) + raw[i] * SMOOTHING;
-        // }
         /// 应用程序主结构体
         ///
         /// 包含所有渲染相关的状态和资源
@@ -81,9 +143,16 @@ pub fn run(shared: SharedPipe) {
             pipeline: Option<wgpu::RenderPipeline>, // 渲染管线
             config: Option<SurfaceConfiguration>,   // 表面配置
             t: f32,                                 // 时间计数器
-            smooth_bands: Vec<f32>,                 // 平滑频段数据
+            split_stereo: bool,                     // 是否左右声道分开显示
+            loudness_meter: bool,                   // 是否显示响度表
+            title: String,                          // 当前窗口标题
+            peaks: Option<(PeakHold, PeakHold)>,    // 上方和下方柱状图的峰值帽
+            last_frame: Option<Instant>,            // 上次重绘的时刻
+            upper: Vec<f32>,                        // 上方柱状图高度比例，每次重绘原地更新
+            lower: Vec<f32>,                        // 下方柱状图高度比例，每次重绘原地更新
             shared: SharedPipe,                     // 频谱数据管道
             vertex_buffer: Option<wgpu::Buffer>,    // 顶点缓冲区
+            bars: usize,                            // 频谱柱数量（与频段数一致）
             max_vertices: usize,                    // 最大顶点数
         }
         impl ApplicationHandler for App {
@@ -92,14 +161,15 @@ pub fn run(shared: SharedPipe) {
             /// 初始化窗口和基本渲染资源
             fn resumed(&mut self, event_loop: &ActiveEventLoop) {
                 // 创建主窗口
-                let attrs = WindowAttributes::default().with_title("Explore Demo");
+                let attrs = WindowAttributes::default().with_title(&self.title);
                 let window = event_loop.create_window(attrs).unwrap();
                 self.window = Some(window);
 
                 // 初始化WGPU实例
                 self.instance = Some(Instance::default());
                 self.t = 0.0; // 重置时间计数器
-                self.max_vertices = BANDS * 6; // 预估最大顶点数
+                self.max_vertices =
+                    max_vertices(self.bars, self.loudness_meter, self.peaks.is_some()); // 每帧顶点数上限
             }
             /// 处理窗口事件
             ///
@@ -240,40 +310,68 @@ pub fn run(shared: SharedPipe) {
                                     .create_command_encoder(&CommandEncoderDescriptor::default());
                                 // 预分配顶点容器以提高性能
                                 let mut vertices = Vec::with_capacity(self.max_vertices);
-                                let bars = 64; // 要显示的频谱柱数量
-                                let raw = self.shared.read(); // 从共享管道读取最新的频谱数据
-                                const SMOOTHING: f32 = 0.03; // 频谱数据平滑系数
+                                let bars = self.bars; // 要显示的频谱柱数量
+                                // 从共享管道读取最新的频谱数据（已在DSP线程按时间常数平滑）
+                                let frame = self.shared.read();
 
-                                // 为每个频段生成对应的可视化柱状图
-                                for i in 0..BANDS.min(bars) {
-                                    // 根据频段位置应用不同的平滑系数
-                                    // 低频段使用更强的平滑效果以减少抖动
-                                    let freq_smooth = if i < BANDS / 6 {
-                                        SMOOTHING // * 3.0 // 低频段三倍平滑强度
-                                    } else {
-                                        SMOOTHING // 其他频段正常使用平滑
-                                    };
+                                // 起音后中心装饰线短暂加粗，按音频流时间衰减
+                                // 两次起音之间按估计的节拍相位继续律动
+                                let pulse =
+                                    frame.onsets.last(OnsetBand::Overall).map_or(0.0, |onset| {
+                                        let age = (frame.time - onset.time).max(0.0) as f32;
+                                        onset.strength.min(2.0) * (-age / ONSET_PULSE_SECS).exp()
+                                    });
+                                let pulse = pulse.max(frame.tempo.pulse(BEAT_PULSE_SHARPNESS));
+
+                                // 处理频谱值并应用非线性变换增强视觉效果
+                                // 限制值域到[0,1]后用双曲正切函数增强对比度，得到相对满柱高的比例
+                                let bar_level = |v: f32| (v.clamp(0.0, 1.0) * 3.0).tanh();
+                                // 分开显示时上方取左声道、下方取右声道，否则上下均取左右平均
+                                self.upper.clear();
+                                self.lower.clear();
+                                if self.split_stereo {
+                                    self.upper.extend(frame.left.iter().map(|&v| bar_level(v)));
+                                    self.lower.extend(frame.right.iter().map(|&v| bar_level(v)));
+                                } else {
+                                    self.upper.extend(
+                                        frame
+                                            .left
+                                            .iter()
+                                            .zip(&frame.right)
+                                            .map(|(l, r)| bar_level(0.5 * (l + r))),
+                                    );
+                                    self.lower.extend_from_slice(&self.upper);
+                                }
 
-                                    // 应用指数移动平均滤波器进行数据平滑
-                                    // 公式：y[n] = α×x[n] + (1-α)×y[n-1]
-                                    self.smooth_bands[i] = self.smooth_bands[i]
-                                        * (1.0 - freq_smooth)
-                                        + raw[i] * freq_smooth;
+                                // 峰值帽按两次重绘之间真实经过的时间推进，与帧率无关
+                                let now = Instant::now();
+                                let dt = self
+                                    .last_frame
+                                    .map_or(0.0, |last| (now - last).as_secs_f32())
+                                    .min(MAX_FRAME_SECS);
+                                self.last_frame = Some(now);
+                                let caps = self.peaks.as_mut().map(|(upper_caps, lower_caps)| {
+                                    (
+                                        upper_caps.update(&self.upper, dt),
+                                        lower_caps.update(&self.lower, dt),
+                                    )
+                                });
+
+                                // 为每个频段生成对应的可视化柱状图
+                                for (i, (&upper_value, &lower_value)) in
+                                    self.upper.iter().zip(&self.lower).enumerate().take(bars)
+                                {
                                     // 计算当前柱状图的水平位置坐标
                                     let x0 = -1.0 + 2.0 * i as f32 / bars as f32; // 左边界 [-1.0, 1.0]
                                     let x1 = x0 + 2.0 / bars as f32 * 0.8; // 右边界（占80%宽度）
 
-                                    // 处理频谱值并应用非线性变换增强视觉效果
-                                    let v = self.smooth_bands[i].clamp(0.0, 1.0); // 限制值域到[0,1]
-                                    let h = (v * 3.0).tanh(); // 双曲正切函数增强对比度
-                                    let half = h * 0.5; // 柱状图高度的一半
                                     // 定义柱状图四个关键点的垂直坐标
                                     let y_top_0 = 0.0; // 上方柱状图底部（Y=0）
-                                    let y_top_1 = half; // 上方柱状图顶部
+                                    let y_top_1 = upper_value * MAX_BAR_HEIGHT; // 上方柱状图顶部
                                     let y_bot_0 = 0.0; // 下方柱状图顶部（Y=0）
-                                    let y_bot_1 = -half; // 下方柱状图底部
+                                    let y_bot_1 = -lower_value * MAX_BAR_HEIGHT; // 下方柱状图底部
                                     // 中心水平装饰线的几何参数
-                                    let line_thickness = 0.01; // 装饰线的垂直厚度
+                                    let line_thickness = 0.01 * (1.0 + pulse); // 装饰线的垂直厚度
                                     let line_left = -1.0; // 线条左端点（屏幕左边界）
                                     let line_right = 1.0; // 线条右端点（屏幕右边界）
                                     vertices.extend_from_slice(&[
@@ -332,47 +430,80 @@ pub fn run(shared: SharedPipe) {
                                             position: [line_left, line_thickness],
                                         },
                                     ]);
+                                    // 峰值帽：上方柱顶之上、下方镜像柱底之下各一个
+                                    if let Some((upper_caps, lower_caps)) = caps {
+                                        let top = upper_caps[i] * MAX_BAR_HEIGHT + CAP_GAP;
+                                        let bottom = -(lower_caps[i] * MAX_BAR_HEIGHT + CAP_GAP);
+                                        vertices.extend_from_slice(&rect(
+                                            x0,
+                                            top,
+                                            x1,
+                                            top + CAP_THICKNESS,
+                                        ));
+                                        vertices.extend_from_slice(&rect(
+                                            x0,
+                                            bottom - CAP_THICKNESS,
+                                            x1,
+                                            bottom,
+                                        ));
+                                    }
                                 }
-                                // 更新或创建顶点缓冲区
-                                let vertex_buffer =
-                                    if let Some(ref existing_buffer) = self.vertex_buffer {
-                                        // 如果已有缓冲区，使用暂存缓冲区进行更新
-                                        let staging_buffer =
-                                            device.create_buffer_init(&BufferInitDescriptor {
-                                                label: Some("顶点数据暂存缓冲区"),
-                                                contents: bytemuck::cast_slice(&vertices),
-                                                usage: wgpu::BufferUsages::COPY_SRC, // 用作复制源
-                                            });
+                                // 响度表和标题栏读数，标题只在读数变化时更新
+                                if self.loudness_meter {
+                                    loudness_meter_vertices(&frame.loudness, &mut vertices);
+                                    let loudness = &frame.loudness;
+                                    let title = format!(
+                                        "{}  |  M {:.1}  S {:.1}  I {:.1} LUFS  LRA {:.1} LU  TP {:.1} dBTP",
+                                        TITLE,
+                                        loudness.momentary,
+                                        loudness.short_term,
+                                        loudness.integrated,
+                                        loudness.range,
+                                        loudness.true_peak
+                                    );
+                                    if title != self.title {
+                                        window.set_title(&title);
+                                        self.title = title;
+                                    }
+                                }
+                                // 首次渲染时按最大顶点数创建顶点缓冲区
+                                let max_vertices = self.max_vertices;
+                                let vertex_buffer = self.vertex_buffer.get_or_insert_with(|| {
+                                    device.create_buffer(&wgpu::BufferDescriptor {
+                                        label: Some("频谱柱顶点缓冲区"),
+                                        size: (max_vertices * size_of::<Vertex>()) as u64,
+                                        usage: wgpu::BufferUsages::VERTEX      // 顶点缓冲区用途
+                                            | wgpu::BufferUsages::COPY_DST, // 可接受复制目标
+                                        mapped_at_creation: false,
+                                    })
+                                });
+                                // 使用暂存缓冲区更新顶点数据
+                                if !vertices.is_empty() {
+                                    let staging_buffer =
+                                        device.create_buffer_init(&BufferInitDescriptor {
+                                            label: Some("顶点数据暂存缓冲区"),
+                                            contents: bytemuck::cast_slice(&vertices),
+                                            usage: wgpu::BufferUsages::COPY_SRC, // 用作复制源
+                                        });
 
-                                        // 创建命令编码器执行缓冲区复制
-                                        let mut encoder = device.create_command_encoder(
-                                            &CommandEncoderDescriptor { label: None },
-                                        );
+                                    // 创建命令编码器执行缓冲区复制
+                                    let mut encoder =
+                                        device.create_command_encoder(&CommandEncoderDescriptor {
+                                            label: None,
+                                        });
 
-                                        // 执行缓冲区数据复制
-                                        encoder.copy_buffer_to_buffer(
-                                            &staging_buffer,
-                                            0,
-                                            existing_buffer,
-                                            0,
-                                            (vertices.len() * size_of::<Vertex>()) as u64,
-                                        );
+                                    // 执行缓冲区数据复制
+                                    encoder.copy_buffer_to_buffer(
+                                        &staging_buffer,
+                                        0,
+                                        &*vertex_buffer,
+                                        0,
+                                        (vertices.len() * size_of::<Vertex>()) as u64,
+                                    );
 
-                                        // 提交复制命令
-                                        queue.submit(Some(encoder.finish()));
-                                        existing_buffer
-                                    } else {
-                                        // 首次创建顶点缓冲区
-                                        let buffer =
-                                            device.create_buffer_init(&BufferInitDescriptor {
-                                                label: Some("频谱柱顶点缓冲区"),
-                                                contents: bytemuck::cast_slice(&vertices),
-                                                usage: wgpu::BufferUsages::VERTEX      // 顶点缓冲区用途
-                                                    | wgpu::BufferUsages::COPY_DST, // 可接受复制目标
-                                            });
-                                        self.vertex_buffer = Some(buffer);
-                                        self.vertex_buffer.as_ref().unwrap()
-                                    };
+                                    // 提交复制命令
+                                    queue.submit(Some(encoder.finish()));
+                                }
                                 // 开始渲染通道
                                 {
                                     let mut rpass =
@@ -419,6 +550,7 @@ pub fn run(shared: SharedPipe) {
             }
         }
         let event_loop = EventLoop::new().unwrap();
+        let bars = shared.bands();
         let mut app = App {
             window: None,
             instance: None,
@@ -428,10 +560,18 @@ pub fn run(shared: SharedPipe) {
             pipeline: None,
             config: None,
             t: 0.0,
-            smooth_bands: vec![0.0f32; BANDS],
+            split_stereo,
+            loudness_meter,
+            title: TITLE.to_string(),
             shared,
             vertex_buffer: None,
-            max_vertices: BANDS * 6,
+            bars,
+            max_vertices: max_vertices(bars, loudness_meter, peaks.is_some()),
+            peaks: peaks
+                .map(|settings| (PeakHold::new(settings, bars), PeakHold::new(settings, bars))),
+            last_frame: None,
+            upper: Vec::new(),
+            lower: Vec::new(),
         };
         let _ = event_loop.run_app(&mut app);
     });